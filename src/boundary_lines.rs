@@ -0,0 +1,242 @@
+// plate boundaries drawn as thick lines along the sides between plates, over the faces rather
+// than instead of them, whenever the map mode shows boundaries
+
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+
+use crate::elevation::Elevation;
+use crate::map_mode::{MapMode, boundary_colour};
+use crate::relief::ReliefSettings;
+use crate::removed::AnyRemoved;
+use crate::worldgen::{Plate, PlateBoundary};
+
+/// Width of the boundary lines in pixels
+const LINE_WIDTH: f32 = 4.0;
+/// Lines float a little above the faces so they aren't hidden in them
+const LIFT: f32 = 1.001;
+/// Ends of neighbouring sides closer than this are the same point
+const JOIN_TOLERANCE: f32 = 1e-5;
+
+#[derive(Default, Reflect, GizmoConfigGroup)]
+struct BoundaryGizmos;
+
+/// A point along a boundary line, on the unit sphere, raised to the higher face beside it
+#[derive(Clone, Copy)]
+struct LinePoint {
+    position: Vec3,
+    elevation: f32,
+    colour: Color,
+}
+
+/// The boundaries as runs of joined up sides, so the thick lines bend round corners without gaps
+#[derive(Resource, Default)]
+struct BoundaryLines(Vec<Vec<LinePoint>>);
+
+/// One side between two plates, drawn in the colour of how the side is classified
+struct Segment {
+    ends: (Vec3, Vec3),
+    elevation: f32,
+    colour: Color,
+}
+
+type BoundaryLinesChanged = Or<(Changed<PlateBoundary>, Changed<Elevation>)>;
+
+/// Join the sides up into lines again whenever the boundaries, their kinds or the heights change
+fn update_boundary_lines(
+    mut lines: ResMut<BoundaryLines>,
+    q_boundaries: Query<(Entity, &PlateBoundary, Option<&Elevation>)>,
+    q_across: Query<Option<&Elevation>, With<Plate>>,
+    changed_faces: Query<(), BoundaryLinesChanged>,
+    mut removed: (
+        RemovedComponents<PlateBoundary>,
+        RemovedComponents<Elevation>,
+    ),
+) {
+    if changed_faces.is_empty() && !removed.any_removed() {
+        return;
+    }
+
+    let mut segments = Vec::new();
+    for (entity_id, boundary, elevation) in q_boundaries.iter() {
+        for side in boundary.own_sides(entity_id) {
+            let Ok(across_elevation) = q_across.get(side.across) else {
+                continue;
+            };
+            let height = |elevation: Option<&Elevation>| elevation.map_or(0.0, |e| e.0);
+            segments.push(Segment {
+                ends: side.ends,
+                elevation: height(elevation).max(height(across_elevation)),
+                colour: boundary_colour(side),
+            });
+        }
+    }
+
+    lines.0 = join_segments(&segments);
+}
+
+/// Chain segments that share an end into lines, a line stops where three plates meet
+fn join_segments(segments: &[Segment]) -> Vec<Vec<LinePoint>> {
+    let key = |point: Vec3| (point / JOIN_TOLERANCE).round().as_ivec3();
+    let mut at_point: HashMap<IVec3, Vec<usize>> = HashMap::new();
+    for (i, segment) in segments.iter().enumerate() {
+        at_point.entry(key(segment.ends.0)).or_default().push(i);
+        at_point.entry(key(segment.ends.1)).or_default().push(i);
+    }
+
+    let mut used = vec![false; segments.len()];
+    // the next unused segment at `point` and its far end, none where more than two sides meet
+    let next_from = |point: Vec3, used: &mut Vec<bool>| {
+        let at = at_point.get(&key(point)).filter(|at| at.len() <= 2)?;
+        let i = *at.iter().find(|i| !used[**i])?;
+        used[i] = true;
+        let segment = &segments[i];
+        let far = if key(segment.ends.0) == key(point) {
+            segment.ends.1
+        } else {
+            segment.ends.0
+        };
+        Some((far, segment))
+    };
+
+    let mut lines = Vec::new();
+    for (start, segment) in segments.iter().enumerate() {
+        if used[start] {
+            continue;
+        }
+        used[start] = true;
+        let point = |position, segment: &Segment| LinePoint {
+            position,
+            elevation: segment.elevation,
+            colour: segment.colour,
+        };
+
+        // walk forwards from the second end, then backwards from the first
+        let mut forwards = vec![
+            point(segment.ends.0, segment),
+            point(segment.ends.1, segment),
+        ];
+        while let Some((far, next)) = next_from(forwards[forwards.len() - 1].position, &mut used) {
+            extend_line(&mut forwards, far, next);
+        }
+        let mut backwards = vec![forwards[0]];
+        while let Some((far, next)) = next_from(backwards[backwards.len() - 1].position, &mut used)
+        {
+            extend_line(&mut backwards, far, next);
+        }
+
+        backwards.reverse();
+        backwards.extend(forwards.into_iter().skip(1));
+        lines.push(backwards);
+    }
+    lines
+}
+
+/// Carry a line on along `next` to its far end. Where the colour changes the joining point is
+/// doubled up in the new colour, so each side is drawn in its own colour rather than fading
+/// into the next
+fn extend_line(line: &mut Vec<LinePoint>, far: Vec3, next: &Segment) {
+    let last = line.len() - 1;
+    line[last].elevation = line[last].elevation.max(next.elevation);
+    if line[last].colour != next.colour {
+        line.push(LinePoint {
+            colour: next.colour,
+            ..line[last]
+        });
+    }
+    line.push(LinePoint {
+        position: far,
+        elevation: next.elevation,
+        colour: next.colour,
+    });
+}
+
+fn draw_boundary_lines(
+    mut gizmos: Gizmos<BoundaryGizmos>,
+    lines: Res<BoundaryLines>,
+    relief: Res<ReliefSettings>,
+) {
+    for line in &lines.0 {
+        gizmos.linestrip_gradient(line.iter().map(|point| {
+            (
+                point.position * relief.radius(point.elevation) * LIFT,
+                point.colour,
+            )
+        }));
+    }
+}
+
+/// Draws the plate boundaries as lines in the map modes that show them
+pub struct BoundaryLinesPlugin;
+
+impl Plugin for BoundaryLinesPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_gizmo_config(
+            BoundaryGizmos,
+            GizmoConfig {
+                line: GizmoLineConfig {
+                    width: LINE_WIDTH,
+                    joints: GizmoLineJoint::Round(4),
+                    ..default()
+                },
+                ..default()
+            },
+        )
+        .init_resource::<BoundaryLines>()
+        .add_systems(
+            Update,
+            (
+                update_boundary_lines,
+                draw_boundary_lines.run_if(|mode: Res<MapMode>| mode.shows_boundaries()),
+            )
+                .chain(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(from: Vec3, to: Vec3, colour: Color) -> Segment {
+        Segment {
+            ends: (from, to),
+            elevation: 0.0,
+            colour,
+        }
+    }
+
+    #[test]
+    fn lines_stop_where_three_plates_meet() {
+        let red = Color::srgb(1.0, 0.0, 0.0);
+        let segments = [
+            segment(Vec3::ZERO, Vec3::X, red),
+            segment(Vec3::ZERO, Vec3::Y, red),
+            segment(Vec3::ZERO, Vec3::Z, red),
+            segment(Vec3::Z, Vec3::ONE, red),
+        ];
+        let lines = join_segments(&segments);
+        assert_eq!(lines.len(), 3);
+        assert!(lines.iter().any(|line| line.len() == 3));
+    }
+
+    #[test]
+    fn each_side_keeps_its_own_colour() {
+        let red = Color::srgb(1.0, 0.0, 0.0);
+        let blue = Color::srgb(0.0, 0.0, 1.0);
+        let segments = [
+            segment(Vec3::X, Vec3::Y, red),
+            segment(Vec3::Y, Vec3::Z, blue),
+        ];
+        let lines = join_segments(&segments);
+        assert_eq!(lines.len(), 1);
+
+        // every stretch of line between two different points runs between points of one colour
+        let line = &lines[0];
+        assert_eq!(line.len(), 4);
+        assert!(
+            line.windows(2)
+                .filter(|pair| pair[0].position != pair[1].position)
+                .all(|pair| pair[0].colour == pair[1].colour)
+        );
+    }
+}
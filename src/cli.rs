@@ -0,0 +1,98 @@
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+use crate::config::Projector;
+use crate::export::MapProjection;
+use crate::map_mode::MapMode;
+
+/// Generate a sphere of hexagons and pentagons, render it nicely
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Seed for world generation, the same seed always gives the same planet
+    #[arg(long, global = true)]
+    pub seed: Option<u64>,
+
+    /// RON file to read the world generation config from
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Number of tectonic plates, overrides the config file
+    #[arg(long, global = true)]
+    pub plates: Option<usize>,
+
+    /// Edge subdivisions of the icosphere (multiple of 3), overrides the config file
+    #[arg(long, global = true)]
+    pub subdivisions: Option<u32>,
+
+    /// Fraction of the globe that is continental crust, overrides the config file
+    #[arg(long, global = true)]
+    pub land_fraction: Option<f32>,
+
+    /// Projector used to lay the hexagons onto the sphere, overrides the config file
+    #[arg(long, value_enum, global = true)]
+    pub projector: Option<Projector>,
+
+    /// Start from a saved world instead of generating a new one, its seed and config win over any others
+    #[arg(long, global = true)]
+    pub load: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Run world generation to completion without a window and write the result to a file
+    Generate(GenerateArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct GenerateArgs {
+    /// Where to write the generated world as JSON
+    #[arg(long)]
+    pub out: PathBuf,
+    /// Also save the world so it can be loaded back later
+    #[arg(long)]
+    pub save: Option<PathBuf>,
+    /// Also write the faces as GeoJSON polygons to this file
+    #[arg(long)]
+    pub geojson: Option<PathBuf>,
+    /// Also draw the world as a flat map and write it to this PNG file
+    #[arg(long)]
+    pub png: Option<PathBuf>,
+    /// Width of the map image in pixels, the height is half of it
+    #[arg(long, default_value_t = 2048)]
+    pub png_width: u32,
+    /// How the map image flattens the globe
+    #[arg(long, value_enum, default_value_t)]
+    pub png_projection: MapProjection,
+    /// Which map mode colours the map image
+    #[arg(long, value_enum, default_value = "elevation")]
+    pub png_mode: MapMode,
+    /// Also write the globe as a binary glTF model to this file
+    #[arg(long)]
+    pub glb: Option<PathBuf>,
+    /// Which map mode colours the glTF model
+    #[arg(long, value_enum, default_value = "elevation")]
+    pub glb_mode: MapMode,
+    /// Raise faces by their elevation, this many times taller than life
+    #[arg(long)]
+    pub glb_relief: Option<f32>,
+    /// Include the face outlines as lines in the glTF model
+    #[arg(long)]
+    pub glb_outlines: bool,
+    /// Fit the sea level so this fraction of the globe is land, in place of the config's `land_fraction`
+    #[arg(long, value_parser = parse_fraction)]
+    pub land_target: Option<f32>,
+}
+
+/// A share of the globe, from 0 to 1
+fn parse_fraction(arg: &str) -> Result<f32, String> {
+    let fraction: f32 = arg.parse().map_err(|e| format!("{e}"))?;
+    if (0.0..=1.0).contains(&fraction) {
+        Ok(fraction)
+    } else {
+        Err("must be between 0 and 1".into())
+    }
+}
@@ -0,0 +1,224 @@
+use bevy::prelude::*;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::{fmt, fs, num::NonZero, path::Path};
+use subsphere::prelude::*;
+
+use crate::cli::Cli;
+
+/// Largest a growth strategy's strength can be, past this every pick but the most favoured is
+/// already turned down as often as it can be
+const MAX_GROWTH_STRENGTH: f32 = 50.0;
+/// Largest power law exponent, past this every plate grows at about the same rate
+const MAX_GROWTH_EXPONENT: f32 = 10.0;
+
+/// Which `subsphere` projector to use when laying the hexagons onto the sphere
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+pub enum Projector {
+    Fuller,
+    Gnomonic,
+}
+
+/// A way of biasing how plates grow during the flood fill, any number of them can be stacked
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum GrowthStrategy {
+    /// Each plate grows at its own rate drawn from a power law, giving a few giant plates
+    /// and many microplates. Smaller exponents spread the sizes further, around 2 looks earthlike
+    PowerLaw { exponent: f32 },
+    /// Each plate prefers to stretch out along its own great circle, 0 is no preference
+    Directional { strength: f32 },
+    /// Plates grow easily where their own noise field is high and slowly where it is low,
+    /// giving ragged boundaries. `scale` is how many blobs of noise fit across the globe
+    Noise { scale: f32, strength: f32 },
+}
+
+/// How the globe is split into plates once their seeds are picked
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum PlatePartition {
+    /// Plates grow out from their seeds a little each tick, shaped by `plate_growth`
+    #[default]
+    FloodFill,
+    /// Every face goes to its nearest seed by great circle distance, all at once.
+    /// Rounds of Lloyd `relaxation` even out the plate sizes, and `warp` bends the
+    /// boundaries with noise `warp_scale` blobs across the globe
+    Voronoi {
+        relaxation: u32,
+        warp: f32,
+        warp_scale: f32,
+    },
+}
+
+/// Parameters for building the globe and generating the world on it
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorldGenConfig {
+    /// Number of tectonic plates to grow
+    pub n_plates: usize,
+    /// Number of segments each icosahedron edge is split into, must be a multiple of 3
+    pub subdivisions: u32,
+    /// Fraction of the globe's faces that are continental crust
+    #[serde(alias = "land_plate_fraction")]
+    pub land_fraction: f32,
+    /// Number of cratons continents grow out from, scattered through the plates' interiors
+    pub n_cratons: usize,
+    /// How ragged the coasts are, 0 grows continents out evenly from their cratons
+    pub coast_roughness: f32,
+    /// How many blobs of coast noise fit across the globe
+    pub coast_noise_scale: f32,
+    pub projector: Projector,
+    pub plate_partition: PlatePartition,
+    /// Plates with fewer faces than this are merged into a neighbour once they're grown
+    pub min_plate_size: usize,
+    /// How plates are biased as they grow, none of them gives round plates of similar size
+    pub plate_growth: Vec<GrowthStrategy>,
+}
+
+impl Default for WorldGenConfig {
+    fn default() -> Self {
+        Self {
+            n_plates: 40,
+            subdivisions: 60,
+            land_fraction: 0.3,
+            n_cratons: 12,
+            coast_roughness: 5.0,
+            coast_noise_scale: 4.0,
+            projector: Projector::Fuller,
+            plate_partition: PlatePartition::FloodFill,
+            min_plate_size: 0,
+            plate_growth: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    Invalid(&'static str),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "could not read config file: {e}"),
+            Self::Parse(e) => write!(f, "could not parse config file: {e}"),
+            Self::Invalid(reason) => write!(f, "invalid config: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl WorldGenConfig {
+    /// Read a config from a RON file, any missing fields take their default value
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let contents = fs::read_to_string(path).map_err(ConfigError::Io)?;
+        ron::from_str(&contents).map_err(ConfigError::Parse)
+    }
+
+    /// Build the config from an optional file, then apply any CLI overrides on top
+    pub fn from_cli(cli: &Cli) -> Result<Self, ConfigError> {
+        let mut config = match &cli.config {
+            Some(path) => Self::from_file(path)?,
+            None => Self::default(),
+        };
+
+        if let Some(n_plates) = cli.plates {
+            config.n_plates = n_plates;
+        }
+        if let Some(subdivisions) = cli.subdivisions {
+            config.subdivisions = subdivisions;
+        }
+        if let Some(land_fraction) = cli.land_fraction {
+            config.land_fraction = land_fraction;
+        }
+        if let Some(projector) = cli.projector {
+            config.projector = projector;
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// How many faces the globe will have, `None` if the subdivisions can't make one
+    pub fn n_faces(&self) -> Option<usize> {
+        let icosphere = subsphere::icosphere().subdivide_edge(NonZero::new(self.subdivisions)?);
+        // the projector only moves the faces about, it doesn't change how many there are
+        subsphere::HexSphere::from_kis(icosphere).map(|sphere| sphere.num_faces())
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.n_plates == 0 {
+            return Err(ConfigError::Invalid("n_plates must be at least 1"));
+        }
+        // `HexSphere::from_kis` only accepts subdivisions that are a multiple of 3
+        let Some(n_faces) = self.n_faces() else {
+            return Err(ConfigError::Invalid(
+                "subdivisions must be a non-zero multiple of 3",
+            ));
+        };
+        // every plate needs a face of its own to seed from
+        if self.n_plates > n_faces {
+            return Err(ConfigError::Invalid(
+                "n_plates must not be more than the globe has faces",
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.land_fraction) {
+            return Err(ConfigError::Invalid(
+                "land_fraction must be between 0 and 1",
+            ));
+        }
+        // continents only grow out from cratons
+        if self.n_cratons == 0 && self.land_fraction > 0.0 {
+            return Err(ConfigError::Invalid(
+                "n_cratons must be at least 1 when land_fraction is more than 0",
+            ));
+        }
+        if !(0.0..).contains(&self.coast_roughness)
+            || self.coast_noise_scale.is_nan()
+            || self.coast_noise_scale <= 0.0
+        {
+            return Err(ConfigError::Invalid(
+                "coast_roughness must not be negative and coast_noise_scale must be greater than 0",
+            ));
+        }
+        if let PlatePartition::Voronoi {
+            warp, warp_scale, ..
+        } = self.plate_partition
+            && (!(0.0..).contains(&warp) || warp_scale.is_nan() || warp_scale <= 0.0)
+        {
+            return Err(ConfigError::Invalid(
+                "voronoi warp must not be negative and warp_scale must be greater than 0",
+            ));
+        }
+        for strategy in &self.plate_growth {
+            match *strategy {
+                GrowthStrategy::PowerLaw { exponent }
+                    if exponent.is_nan() || exponent <= 0.0 || exponent > MAX_GROWTH_EXPONENT =>
+                {
+                    return Err(ConfigError::Invalid(
+                        "power law growth exponent must be greater than 0 and at most 10",
+                    ));
+                }
+                GrowthStrategy::Directional { strength }
+                    if !(0.0..=MAX_GROWTH_STRENGTH).contains(&strength) =>
+                {
+                    return Err(ConfigError::Invalid(
+                        "directional growth strength must be between 0 and 50",
+                    ));
+                }
+                GrowthStrategy::Noise { scale, strength }
+                    if !scale.is_finite()
+                        || scale <= 0.0
+                        || !(0.0..=MAX_GROWTH_STRENGTH).contains(&strength) =>
+                {
+                    return Err(ConfigError::Invalid(
+                        "noise growth scale must be greater than 0 and strength between 0 and 50",
+                    ));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
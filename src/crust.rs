@@ -0,0 +1,237 @@
+// continental crust grown out from cratons scattered through the plates, so a plate can carry
+// both continent and ocean floor, everything else is left as ocean floor. Continents keep mostly
+// to the plate their craton is on
+
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use rand::{Rng, seq::index::sample_weighted};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::config::WorldGenConfig;
+use crate::noise::Noise;
+use crate::seed::{RngStream, WorldRng};
+use crate::setup::{Face, FaceNeighbours};
+use crate::states::WorldGenState;
+use crate::worldgen::{Plate, PlateBoundary};
+
+/// Octaves summed for the noise that roughens the coasts
+const COAST_OCTAVES: u32 = 3;
+/// How much dearer each step of a continent is once it has grown off its craton's plate
+const OFF_PLATE_COST: f32 = 4.0;
+
+/// Whether a face is thick, light continent or thin, dense ocean floor
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CrustType {
+    Continental,
+    Oceanic,
+}
+
+/// How thick a face's crust is, in km
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct CrustThickness(pub f32);
+
+impl CrustThickness {
+    pub const OCEANIC: Self = Self(7.0);
+    /// At the heart of a craton, the oldest and thickest continent
+    pub const CRATON: Self = Self(40.0);
+    /// At the edge of a continent, where it thins out into the continental shelf
+    pub const MARGIN: Self = Self(25.0);
+}
+
+/// A face waiting to be claimed, cheapest first
+struct Claim {
+    cost: f32,
+    face: usize,
+    /// Plate of the craton the claim grew from
+    home: usize,
+}
+
+impl PartialEq for Claim {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Claim {}
+
+impl PartialOrd for Claim {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Claim {
+    // the heap pops its largest, so the cheapest claim has to compare largest
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .total_cmp(&self.cost)
+            .then(other.face.cmp(&self.face))
+    }
+}
+
+/// Pick up to `n_cratons` interior faces to seed continents from. Plates are drawn weighted by
+/// how much interior they have, each plate at most once until every plate has had a craton,
+/// so continents are spread over the plates rather than bunched on a few of them
+fn seed_cratons(
+    rng: &mut impl Rng,
+    plates: &[usize],
+    interior: &[bool],
+    n_cratons: usize,
+) -> Vec<usize> {
+    let n_plates = plates.iter().max().map_or(0, |plate| plate + 1);
+    let mut spare: Vec<Vec<usize>> = vec![Vec::new(); n_plates];
+    for (face, &plate) in plates.iter().enumerate() {
+        if interior[face] {
+            spare[plate].push(face);
+        }
+    }
+
+    let mut cratons = Vec::with_capacity(n_cratons);
+    while cratons.len() < n_cratons {
+        let weight = |plate: usize| u32::try_from(spare[plate].len()).unwrap_or(u32::MAX);
+        let Ok(drawn) = sample_weighted(rng, n_plates, weight, n_cratons - cratons.len()) else {
+            break;
+        };
+        // no plate has any interior left
+        if drawn.is_empty() {
+            break;
+        }
+        for plate in drawn {
+            let i = rng.random_range(0..spare[plate].len());
+            cratons.push(spare[plate].swap_remove(i));
+        }
+    }
+    cratons
+}
+
+/// Grow continents out from the `cratons` until `target` faces are continental, each step costing
+/// its length on the unit sphere, cheaper where the coast noise is high so coasts come out ragged
+/// and dearer off the craton's own plate.
+/// Returns how far each continental face is from its craton in that cost, `None` for ocean
+fn grow_cratons(
+    centres: &[Vec3],
+    neighbours: &[Vec<usize>],
+    plates: &[usize],
+    cratons: &[usize],
+    target: usize,
+    noise: Noise,
+    config: &WorldGenConfig,
+) -> Vec<Option<f32>> {
+    let mut costs = vec![None; centres.len()];
+    let mut frontier: BinaryHeap<Claim> = cratons
+        .iter()
+        .map(|&face| Claim {
+            cost: 0.0,
+            face,
+            home: plates[face],
+        })
+        .collect();
+
+    let mut claimed = 0;
+    while claimed < target
+        && let Some(Claim { cost, face, home }) = frontier.pop()
+    {
+        if costs[face].is_some() {
+            continue;
+        }
+        costs[face] = Some(cost);
+        claimed += 1;
+
+        for &neighbour in &neighbours[face] {
+            if costs[neighbour].is_some() {
+                continue;
+            }
+            let roughness = noise.fbm(centres[neighbour] * config.coast_noise_scale, COAST_OCTAVES);
+            let mut step = centres[face].distance(centres[neighbour])
+                * (-config.coast_roughness * roughness).exp();
+            if plates[neighbour] != home {
+                step *= OFF_PLATE_COST;
+            }
+            frontier.push(Claim {
+                cost: cost + step,
+                face: neighbour,
+                home,
+            });
+        }
+    }
+    costs
+}
+
+/// Seed cratons away from the plate boundaries, spread over the plates, and grow continents out
+/// from them until the configured share of the globe is continental, the rest is ocean floor
+pub(crate) fn gen_crust(
+    mut commands: Commands,
+    mut state: ResMut<NextState<WorldGenState>>,
+    mut world_rng: ResMut<WorldRng>,
+    config: Res<WorldGenConfig>,
+    query_faces: Query<(Entity, &Face, &FaceNeighbours, &Plate, Has<PlateBoundary>)>,
+) {
+    let rng = world_rng.stream(RngStream::Continents);
+
+    // lay the faces out in index order so the result doesn't depend on the ECS's layout
+    let mut faces: Vec<_> = query_faces.iter().collect();
+    faces.sort_unstable_by_key(|(_, face, ..)| face.index);
+    let slots: HashMap<Entity, usize> = faces
+        .iter()
+        .enumerate()
+        .map(|(i, (entity_id, ..))| (*entity_id, i))
+        .collect();
+    let centres: Vec<Vec3> = faces
+        .iter()
+        .map(|(_, face, ..)| face.centre_pos.normalize())
+        .collect();
+    let neighbours: Vec<Vec<usize>> = faces
+        .iter()
+        .map(|(_, _, face_neighbours, ..)| {
+            face_neighbours
+                .iter()
+                .filter_map(|entity_id| slots.get(entity_id).copied())
+                .collect()
+        })
+        .collect();
+
+    let plates: Vec<usize> = faces.iter().map(|(_, _, _, plate, _)| plate.0).collect();
+
+    // cratons are the stable hearts of plates, so they're only seeded away from the boundaries
+    let interior: Vec<bool> = faces.iter().map(|(.., boundary)| !boundary).collect();
+    let mut cratons = seed_cratons(rng, &plates, &interior, config.n_cratons);
+    // with plates so small they're all boundary there's no interior, so make do with any face
+    if cratons.is_empty() && config.n_cratons > 0 {
+        warn!("No plate has any faces away from its boundary, seeding cratons anywhere instead");
+        cratons = seed_cratons(rng, &plates, &vec![true; plates.len()], config.n_cratons);
+    }
+    let noise = Noise::new(rng.random());
+
+    #[allow(clippy::cast_precision_loss, clippy::cast_sign_loss)]
+    let target = (faces.len() as f32 * config.land_fraction).round() as usize;
+    let costs = grow_cratons(
+        &centres,
+        &neighbours,
+        &plates,
+        &cratons,
+        target,
+        noise,
+        &config,
+    );
+
+    // crust thins from the cratons out to the coast
+    let furthest = costs.iter().flatten().copied().fold(0.0, f32::max);
+    for ((entity_id, ..), cost) in faces.iter().zip(costs) {
+        let (crust, thickness) = match cost {
+            Some(cost) => {
+                let inland = 1.0 - cost / furthest.max(f32::EPSILON);
+                let thickness = CrustThickness::MARGIN
+                    .0
+                    .lerp(CrustThickness::CRATON.0, inland);
+                (CrustType::Continental, CrustThickness(thickness))
+            }
+            None => (CrustType::Oceanic, CrustThickness::OCEANIC),
+        };
+        commands.entity(*entity_id).insert((crust, thickness));
+    }
+
+    state.set(WorldGenState::FinishedContinents);
+}
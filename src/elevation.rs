@@ -0,0 +1,344 @@
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+
+use crate::crust::{CrustThickness, CrustType};
+use crate::setup::{Face, FaceNeighbours};
+use crate::states::WorldGenState;
+use crate::worldgen::{BoundaryClass, BoundaryKind, FacePlateVelocity, Plate, PlateBoundary};
+
+/// Height of a face in km, measured from where the sea sits by default
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct Elevation(pub f32);
+
+/// Resting height of the thinnest continental crust away from any boundary
+const CONTINENT_BASE: f32 = 0.1;
+/// Height thicker continental crust floats up by for each extra km of thickness
+const ISOSTASY: f32 = 0.06;
+/// Resting depth of the ocean floor away from any boundary
+const OCEAN_BASE: f32 = -4.0;
+
+/// How many faces away from the boundary its influence reaches, it has faded to nothing by then
+const MAX_REACH: u32 = 8;
+/// Rounds of averaging the uplift with the neighbours, to blur the seams where the influence
+/// of two boundaries meets
+const SMOOTHING_PASSES: u32 = 3;
+
+/// Colour ramp from deep ocean through coast and lowland to snowy peaks
+pub fn elevation_colour(elevation: f32) -> Color {
+    let stops: [(f32, Color); 7] = [
+        (-8.0, Color::srgb(0.02, 0.05, 0.25)),
+        (-4.0, Color::srgb(0.0, 0.25, 0.55)),
+        (0.0, Color::srgb(0.45, 0.75, 0.9)),
+        (0.01, Color::srgb(0.3, 0.6, 0.25)),
+        (1.5, Color::srgb(0.75, 0.7, 0.35)),
+        (4.0, Color::srgb(0.5, 0.35, 0.25)),
+        (7.0, Color::WHITE),
+    ];
+
+    let Some(upper) = stops.iter().position(|(height, _)| *height >= elevation) else {
+        return stops[stops.len() - 1].1;
+    };
+    if upper == 0 {
+        return stops[0].1;
+    }
+    let (low_height, low_colour) = stops[upper - 1];
+    let (high_height, high_colour) = stops[upper];
+    let t = (elevation - low_height) / (high_height - low_height);
+    low_colour.mix(&high_colour, t)
+}
+
+/// What a boundary face does to the terrain around it:
+/// the change in height at the boundary, and how many faces it takes to fade out
+fn boundary_uplift(
+    kind: BoundaryKind,
+    continental: bool,
+    other_continental: bool,
+    subducts: bool,
+) -> (f32, f32) {
+    match (kind, continental, other_continental) {
+        // continents colliding push up wide mountain ranges
+        (BoundaryKind::Convergent, true, true) => (5.5, 4.0),
+        // ocean diving under a continent leaves a trench offshore and volcanic mountains inland
+        (BoundaryKind::Convergent, false, true) => (-4.0, 1.5),
+        (BoundaryKind::Convergent, true, false) => (3.0, 2.5),
+        // ocean under ocean, a trench on the sinking side and an island arc on the other
+        (BoundaryKind::Convergent, false, false) if subducts => (-4.0, 1.5),
+        (BoundaryKind::Convergent, false, false) => (4.5, 1.5),
+        // plates pulling apart raise a ridge under the sea and drop a rift valley on land
+        (BoundaryKind::Divergent, false, _) => (2.0, 3.0),
+        (BoundaryKind::Divergent, true, _) => (-1.5, 1.5),
+        (BoundaryKind::Transform, _, _) => (0.0, 1.0),
+    }
+}
+
+/// What a boundary effect needs to know about the crust on one side of the boundary
+#[derive(Clone, Copy)]
+pub(crate) struct BoundaryCrust {
+    pub continental: bool,
+    pub thickness: f32,
+    /// How fast the plate is moving there
+    pub speed: f32,
+}
+
+impl BoundaryCrust {
+    /// Whether this crust sinks under `other` where they meet. Thinner crust is denser, and
+    /// between ocean floor as thick as each other the faster plate goes under, it's moving fast
+    /// because the slab already sinking is dragging it along
+    fn subducts_under(self, other: BoundaryCrust) -> bool {
+        match self.thickness.total_cmp(&other.thickness) {
+            Ordering::Less => true,
+            Ordering::Greater => false,
+            Ordering::Equal => self.speed > other.speed,
+        }
+    }
+}
+
+/// How far a boundary side raises (or lowers) the face it's on and how many faces that takes
+/// to fade out, from the crust either side of it
+pub(crate) fn boundary_effect(
+    crust: BoundaryCrust,
+    other: BoundaryCrust,
+    class: BoundaryClass,
+) -> (f32, f32) {
+    let (uplift, reach) = boundary_uplift(
+        class.kind,
+        crust.continental,
+        other.continental,
+        crust.subducts_under(other),
+    );
+
+    // faster plates make bigger features
+    (uplift * class.magnitude.clamp(0.25, 1.5), reach)
+}
+
+/// The effect of whichever of a face's boundary sides moves it furthest, `None` without any
+pub(crate) fn strongest_effect(
+    effects: impl IntoIterator<Item = (f32, f32)>,
+) -> Option<(f32, f32)> {
+    effects
+        .into_iter()
+        .max_by(|a, b| a.0.abs().total_cmp(&b.0.abs()))
+}
+
+/// Share of a boundary's uplift left `distance` faces from it, fading over `reach` faces and
+/// tapered to nothing at `MAX_REACH` so there's no step where the influence stops
+fn falloff(distance: u32, reach: f32) -> f32 {
+    #[allow(clippy::cast_precision_loss)]
+    let (distance, max_reach) = (distance as f32, MAX_REACH as f32);
+    (-distance / reach).exp() * (1.0 - distance / max_reach).max(0.0)
+}
+
+/// Average each face's value with its `neighbours`' `passes` times over, leaving `fixed` faces
+/// as they are so the boundaries keep their full height
+fn smooth(values: &[f32], neighbours: &[Vec<usize>], fixed: &[bool], passes: u32) -> Vec<f32> {
+    let mut values = values.to_vec();
+    for _ in 0..passes {
+        values = (0..values.len())
+            .map(|i| {
+                if fixed[i] || neighbours[i].is_empty() {
+                    return values[i];
+                }
+                #[allow(clippy::cast_precision_loss)]
+                let mean = neighbours[i].iter().map(|&j| values[j]).sum::<f32>()
+                    / neighbours[i].len() as f32;
+                0.5 * (values[i] + mean)
+            })
+            .collect();
+    }
+    values
+}
+
+/// Height crust settles at away from any boundary, thicker continent floats higher
+pub(crate) fn base_elevation(crust: CrustType, thickness: CrustThickness) -> f32 {
+    match crust {
+        CrustType::Continental => {
+            CONTINENT_BASE + ISOSTASY * (thickness.0 - CrustThickness::MARGIN.0)
+        }
+        CrustType::Oceanic => OCEAN_BASE,
+    }
+}
+
+/// Raise and lower the crust around each plate boundary, fading out with distance
+pub(crate) fn gen_elevation(
+    mut commands: Commands,
+    q_faces: Query<(Entity, &FaceNeighbours, &Plate, &CrustType, &CrustThickness), With<Face>>,
+    q_boundaries: Query<(Entity, &Face, &PlateBoundary)>,
+    q_crust: Query<(&CrustType, &CrustThickness, &FacePlateVelocity)>,
+    mut state: ResMut<NextState<WorldGenState>>,
+) {
+    // (uplift, reach in faces, distance from its boundary in faces) for every face reached so far
+    let mut reached: HashMap<Entity, (f32, f32, u32)> = HashMap::default();
+    let mut frontier = VecDeque::new();
+
+    // spread from boundaries in face order, so ties between boundaries don't depend on
+    // how the ECS happens to lay out the faces (which differs after loading a save)
+    let mut boundaries: Vec<_> = q_boundaries.iter().collect();
+    boundaries.sort_unstable_by_key(|(_, face, ..)| face.index);
+
+    let boundary_crust = |entity_id| {
+        let (crust, thickness, velocity) = q_crust.get(entity_id).ok()?;
+        Some(BoundaryCrust {
+            continental: *crust == CrustType::Continental,
+            thickness: thickness.0,
+            speed: velocity.velocity.length(),
+        })
+    };
+    for (entity_id, _, boundary) in boundaries {
+        let Some(crust) = boundary_crust(entity_id) else {
+            continue;
+        };
+        let effects = boundary.sides.iter().filter_map(|side| {
+            Some(boundary_effect(
+                crust,
+                boundary_crust(side.across)?,
+                side.class?,
+            ))
+        });
+        let Some((uplift, reach)) = strongest_effect(effects) else {
+            continue;
+        };
+
+        reached.insert(entity_id, (uplift, reach, 0));
+        frontier.push_back(entity_id);
+    }
+
+    // spread each boundary's influence into its own plate, nearest boundary wins
+    while let Some(entity_id) = frontier.pop_front() {
+        let (uplift, reach, distance) = reached[&entity_id];
+        if distance >= MAX_REACH {
+            continue;
+        }
+        let Ok((_, face_neighbours, plate, ..)) = q_faces.get(entity_id) else {
+            continue;
+        };
+        for &neighbour_entity_id in face_neighbours.iter() {
+            if reached.contains_key(&neighbour_entity_id) {
+                continue;
+            }
+            if let Ok((_, _, neighbour_plate, ..)) = q_faces.get(neighbour_entity_id)
+                && neighbour_plate == plate
+            {
+                reached.insert(neighbour_entity_id, (uplift, reach, distance + 1));
+                frontier.push_back(neighbour_entity_id);
+            }
+        }
+    }
+
+    // nearest boundary wins leaves a step where two boundaries' influence meets, smooth it over
+    // within each plate
+    let faces: Vec<_> = q_faces.iter().collect();
+    let slots: HashMap<Entity, usize> = faces
+        .iter()
+        .enumerate()
+        .map(|(i, (entity_id, ..))| (*entity_id, i))
+        .collect();
+    let neighbours: Vec<Vec<usize>> = faces
+        .iter()
+        .map(|(_, face_neighbours, plate, ..)| {
+            face_neighbours
+                .iter()
+                .filter_map(|entity_id| slots.get(entity_id).copied())
+                .filter(|&j| faces[j].2 == *plate)
+                .collect()
+        })
+        .collect();
+    let (uplifts, fixed): (Vec<f32>, Vec<bool>) = faces
+        .iter()
+        .map(|(entity_id, ..)| match reached.get(entity_id) {
+            Some(&(uplift, reach, distance)) => (uplift * falloff(distance, reach), distance == 0),
+            None => (0.0, false),
+        })
+        .unzip();
+    let uplifts = smooth(&uplifts, &neighbours, &fixed, SMOOTHING_PASSES);
+
+    for ((entity_id, _, _, crust, thickness), uplift) in faces.iter().zip(uplifts) {
+        let elevation = base_elevation(**crust, **thickness) + uplift;
+        commands.entity(*entity_id).insert(Elevation(elevation));
+    }
+
+    state.set(WorldGenState::JustChill);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-5
+    }
+
+    #[test]
+    fn falloff_is_whole_at_the_boundary_and_gone_at_max_reach() {
+        assert!(close(falloff(0, 2.0), 1.0));
+        assert!(close(falloff(MAX_REACH, 2.0), 0.0));
+        assert!(close(falloff(MAX_REACH + 3, 2.0), 0.0));
+    }
+
+    #[test]
+    fn falloff_fades_with_distance_and_slower_over_longer_reach() {
+        let fades = (0..MAX_REACH).map(|distance| falloff(distance, 3.0));
+        let next = (1..=MAX_REACH).map(|distance| falloff(distance, 3.0));
+        assert!(fades.zip(next).all(|(near, far)| far < near));
+        assert!(falloff(2, 4.0) > falloff(2, 1.0));
+    }
+
+    #[test]
+    fn colliding_continents_raise_mountains() {
+        let (uplift, reach) = boundary_uplift(BoundaryKind::Convergent, true, true, false);
+        assert!(uplift > 0.0);
+        assert!(reach > boundary_uplift(BoundaryKind::Convergent, true, false, false).1);
+    }
+
+    #[test]
+    fn sinking_ocean_makes_a_trench_and_the_overriding_side_an_arc() {
+        let (sinking, _) = boundary_uplift(BoundaryKind::Convergent, false, false, true);
+        let (overriding, _) = boundary_uplift(BoundaryKind::Convergent, false, false, false);
+        assert!(sinking < 0.0);
+        assert!(overriding > 0.0);
+        assert!(boundary_uplift(BoundaryKind::Convergent, false, true, true).0 < 0.0);
+    }
+
+    #[test]
+    fn ridges_under_the_sea_rifts_on_land_and_nothing_at_transforms() {
+        assert!(boundary_uplift(BoundaryKind::Divergent, false, false, false).0 > 0.0);
+        assert!(boundary_uplift(BoundaryKind::Divergent, true, true, false).0 < 0.0);
+        assert!(close(
+            boundary_uplift(BoundaryKind::Transform, true, false, false).0,
+            0.0
+        ));
+    }
+
+    #[test]
+    fn thinner_or_else_faster_crust_subducts() {
+        let crust = |thickness, speed| BoundaryCrust {
+            continental: false,
+            thickness,
+            speed,
+        };
+        assert!(crust(7.0, 0.1).subducts_under(crust(9.0, 0.1)));
+        assert!(!crust(9.0, 0.1).subducts_under(crust(7.0, 0.1)));
+        assert!(crust(7.0, 0.5).subducts_under(crust(7.0, 0.1)));
+        assert!(!crust(7.0, 0.1).subducts_under(crust(7.0, 0.5)));
+    }
+
+    #[test]
+    fn smoothing_keeps_fixed_faces_and_evens_out_a_step() {
+        // a line of faces, the ends held and a step in the middle
+        let neighbours = vec![vec![1], vec![0, 2], vec![1, 3], vec![2, 4], vec![3]];
+        let fixed = [true, false, false, false, true];
+        let values = [2.0, 2.0, 2.0, 0.0, 0.0];
+        let smoothed = smooth(&values, &neighbours, &fixed, 3);
+        assert!(close(smoothed[0], 2.0) && close(smoothed[4], 0.0));
+        assert!(smoothed.windows(2).all(|pair| pair[1] <= pair[0]));
+        assert!(smoothed[2] - smoothed[3] < values[2] - values[3]);
+    }
+
+    #[test]
+    fn smoothing_leaves_flat_ground_flat() {
+        let neighbours = vec![vec![1, 2], vec![0, 2], vec![0, 1]];
+        let smoothed = smooth(&[1.5; 3], &neighbours, &[false; 3], 5);
+        assert!(smoothed.iter().all(|value| close(*value, 1.5)));
+    }
+}
@@ -0,0 +1,75 @@
+// get generated worlds out of the app and into other tools
+
+mod geojson;
+mod gltf;
+mod map_image;
+
+use bevy::math::DVec3;
+use bevy::prelude::*;
+
+use crate::map_mode::MapMode;
+use crate::relief::ReliefSettings;
+
+pub use geojson::ExportGeoJson;
+pub use gltf::ExportGltf;
+pub use map_image::{ExportMapImage, MapProjection};
+
+/// Longitude and latitude in degrees of a point on the unit sphere, with +z as the north pole
+pub fn to_lon_lat(point: Vec3) -> (f64, f64) {
+    let point = point.as_dvec3().normalize();
+    let lat = point.z.clamp(-1.0, 1.0).asin().to_degrees();
+    let lon = point.y.atan2(point.x).to_degrees();
+    (lon, lat)
+}
+
+/// Point on the unit sphere at a longitude and latitude in degrees, the inverse of [`to_lon_lat`]
+pub fn from_lon_lat(lon: f64, lat: f64) -> Vec3 {
+    let (lon, lat) = (lon.to_radians(), lat.to_radians());
+    DVec3::new(lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()).as_vec3()
+}
+
+/// G writes the world to GeoJSON, I to a map image and O to a glTF model of the globe as it looks now,
+/// all in the working directory
+fn handle_export_keys(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mode: Option<Res<MapMode>>,
+    relief: Option<Res<ReliefSettings>>,
+) {
+    let mode = mode.map(|mode| *mode).unwrap_or_default();
+    if keyboard_input.just_pressed(KeyCode::KeyG) {
+        commands.trigger(ExportGeoJson {
+            path: "world.geojson".into(),
+        });
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyI) {
+        commands.trigger(ExportMapImage {
+            path: "world.png".into(),
+            width: 2048,
+            projection: MapProjection::Equirectangular,
+            mode,
+        });
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyO) {
+        commands.trigger(ExportGltf {
+            path: "world.glb".into(),
+            mode,
+            relief: relief.map(|relief| *relief).unwrap_or_default(),
+            outlines: true,
+        });
+    }
+}
+
+pub struct ExportPlugin;
+
+impl Plugin for ExportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_observer(geojson::export_geojson)
+            .add_observer(map_image::export_map_image)
+            .add_observer(gltf::export_gltf)
+            .add_systems(
+                Update,
+                handle_export_keys.run_if(resource_exists::<ButtonInput<KeyCode>>),
+            );
+    }
+}
@@ -0,0 +1,355 @@
+use bevy::prelude::*;
+use serde_json::{Map, Value, json};
+use std::{fs, path::PathBuf};
+
+use super::to_lon_lat;
+use crate::crust::{CrustThickness, CrustType};
+use crate::elevation::Elevation;
+use crate::setup::Face;
+use crate::worldgen::{FacePlateVelocity, Land, Plate, PlateBoundary, Sea};
+
+/// Write every face as a GeoJSON polygon, with whatever has been generated so far as properties
+#[derive(Event)]
+pub struct ExportGeoJson {
+    pub path: PathBuf,
+}
+
+type Ring = Vec<[f64; 2]>;
+
+pub(super) fn export_geojson(
+    export: On<ExportGeoJson>,
+    query_faces: Query<(
+        &Face,
+        Option<&Plate>,
+        Option<&PlateBoundary>,
+        Has<Land>,
+        Has<Sea>,
+        Option<&CrustType>,
+        Option<&CrustThickness>,
+        Option<&Elevation>,
+        Option<&FacePlateVelocity>,
+    )>,
+) {
+    let mut faces: Vec<_> = query_faces.iter().collect();
+    faces.sort_unstable_by_key(|(face, ..)| face.index);
+
+    let features: Vec<Value> = faces
+        .into_iter()
+        .map(
+            |(face, plate, boundary, land, sea, crust, thickness, elevation, velocity)| {
+                let class = boundary.and_then(PlateBoundary::class);
+                let mut properties = Map::new();
+                properties.insert("index".into(), json!(face.index));
+                properties.insert("plate".into(), json!(plate.map(|plate| plate.0)));
+                properties.insert("boundary".into(), json!(boundary.is_some()));
+                properties.insert(
+                    "boundary_kind".into(),
+                    json!(class.map(|class| format!("{:?}", class.kind))),
+                );
+                properties.insert(
+                    "boundary_magnitude".into(),
+                    json!(class.map(|class| class.magnitude)),
+                );
+                // null until continents have been generated
+                let land = match (land, sea) {
+                    (true, _) => Some(true),
+                    (_, true) => Some(false),
+                    _ => None,
+                };
+                properties.insert("land".into(), json!(land));
+                properties.insert(
+                    "crust".into(),
+                    json!(crust.map(|crust| format!("{crust:?}"))),
+                );
+                properties.insert(
+                    "crust_thickness".into(),
+                    json!(thickness.map(|thickness| thickness.0)),
+                );
+                properties.insert(
+                    "elevation".into(),
+                    json!(elevation.map(|elevation| elevation.0)),
+                );
+                properties.insert(
+                    "velocity".into(),
+                    json!(velocity.map(|velocity| velocity.velocity.to_array())),
+                );
+
+                json!({
+                    "type": "Feature",
+                    "geometry": face_geometry(&face.vertices),
+                    "properties": properties,
+                })
+            },
+        )
+        .collect();
+
+    let collection = json!({
+        "type": "FeatureCollection",
+        "features": features,
+    });
+
+    let result = serde_json::to_string(&collection)
+        .map_err(|e| e.to_string())
+        .and_then(|json| fs::write(&export.path, json).map_err(|e| e.to_string()));
+
+    match result {
+        Ok(()) => info!("Wrote GeoJSON to {}", export.path.display()),
+        Err(e) => error!("Could not write GeoJSON to {}: {e}", export.path.display()),
+    }
+}
+
+/// A face as a polygon, or two when it has to be split at the antimeridian
+fn face_geometry(vertices: &[Vec3]) -> Value {
+    let points: Vec<(f64, f64)> = vertices.iter().map(|v| to_lon_lat(*v)).collect();
+
+    // walk around the face so longitude never jumps by more than half the globe
+    let mut ring: Ring = vec![[points[0].0, points[0].1]];
+    for &(lon, lat) in &points[1..] {
+        let previous = ring[ring.len() - 1][0];
+        ring.push([unwrap_lon(lon, previous), lat]);
+    }
+
+    // if we end up a whole turn away from where we started the face surrounds a pole, which way
+    // round it went depends on the vertex order so which pole comes from the latitudes
+    let winding = unwrap_lon(ring[0][0], ring[ring.len() - 1][0]) - ring[0][0];
+    if winding.abs() > 180.0 {
+        let north = points.iter().map(|(_, lat)| lat).sum::<f64>() > 0.0;
+        return polygon(&[polar_cap(&points, north)]);
+    }
+
+    // keep the ring within [-180, ...], then split off anything past 180
+    let min_lon = ring.iter().map(|p| p[0]).fold(f64::INFINITY, f64::min);
+    if min_lon < -180.0 {
+        for point in &mut ring {
+            point[0] += 360.0;
+        }
+    }
+    let max_lon = ring.iter().map(|p| p[0]).fold(f64::NEG_INFINITY, f64::max);
+    if max_lon <= 180.0 {
+        return polygon(&[ring]);
+    }
+
+    let west = clip(&ring, |lon| lon <= 180.0);
+    let mut east = clip(&ring, |lon| lon >= 180.0);
+    for point in &mut east {
+        point[0] -= 360.0;
+    }
+    json!({
+        "type": "MultiPolygon",
+        "coordinates": [[close(west)], [close(east)]],
+    })
+}
+
+fn polygon(rings: &[Ring]) -> Value {
+    let rings: Vec<Ring> = rings.iter().cloned().map(close).collect();
+    json!({
+        "type": "Polygon",
+        "coordinates": rings,
+    })
+}
+
+/// GeoJSON wants rings counter-clockwise on the map, with the first point repeated at the end
+fn close(mut ring: Ring) -> Ring {
+    let twice_area: f64 = (0..ring.len())
+        .map(|i| {
+            let [x0, y0] = ring[i];
+            let [x1, y1] = ring[(i + 1) % ring.len()];
+            x0 * y1 - x1 * y0
+        })
+        .sum();
+    if twice_area < 0.0 {
+        ring.reverse();
+    }
+    if let Some(&first) = ring.first() {
+        ring.push(first);
+    }
+    ring
+}
+
+/// Shift `lon` by whole turns so it's within 180 degrees of `previous`
+fn unwrap_lon(lon: f64, previous: f64) -> f64 {
+    let mut lon = lon;
+    while lon - previous > 180.0 {
+        lon -= 360.0;
+    }
+    while lon - previous < -180.0 {
+        lon += 360.0;
+    }
+    lon
+}
+
+/// Latitude where the edge from `a` to `b` crosses longitude `lon`
+fn lat_at(a: [f64; 2], b: [f64; 2], lon: f64) -> f64 {
+    let t = (lon - a[0]) / (b[0] - a[0]);
+    a[1] + t * (b[1] - a[1])
+}
+
+/// Clip a ring to one side of the antimeridian (Sutherland-Hodgman against a vertical line)
+fn clip(ring: &[[f64; 2]], inside: impl Fn(f64) -> bool) -> Ring {
+    let mut clipped = Vec::new();
+    for (i, &current) in ring.iter().enumerate() {
+        let next = ring[(i + 1) % ring.len()];
+        match (inside(current[0]), inside(next[0])) {
+            (true, true) => clipped.push(current),
+            (true, false) => {
+                clipped.push(current);
+                clipped.push([180.0, lat_at(current, next, 180.0)]);
+            }
+            (false, true) => clipped.push([180.0, lat_at(current, next, 180.0)]),
+            (false, false) => {}
+        }
+    }
+    clipped
+}
+
+/// A face around a pole becomes the band from its edge up to the pole, running right across the map
+fn polar_cap(points: &[(f64, f64)], north: bool) -> Ring {
+    let mut edge: Ring = points.iter().map(|&(lon, lat)| [lon, lat]).collect();
+    edge.sort_by(|a, b| a[0].total_cmp(&b[0]));
+
+    // latitude of the face's edge where it meets the antimeridian
+    let first = edge[0];
+    let last = edge[edge.len() - 1];
+    let wrap_lat = lat_at(last, [first[0] + 360.0, first[1]], 180.0);
+
+    let pole = if north { 90.0 } else { -90.0 };
+    let mut ring = vec![[-180.0, wrap_lat]];
+    ring.extend(edge);
+    ring.push([180.0, wrap_lat]);
+    ring.push([180.0, pole]);
+    ring.push([-180.0, pole]);
+    ring
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::from_lon_lat;
+
+    /// Corners of a hexagon `radius` degrees across around a point, in either winding
+    fn hexagon(lon: f64, lat: f64, radius: f64, clockwise: bool) -> Vec<Vec3> {
+        let mut corners: Vec<Vec3> = (0..6)
+            .map(|i| {
+                let angle = f64::from(i) * 60.0_f64.to_radians();
+                from_lon_lat(lon + radius * angle.cos(), lat + radius * angle.sin())
+            })
+            .collect();
+        if clockwise {
+            corners.reverse();
+        }
+        corners
+    }
+
+    /// Corners of a hexagon around a pole, all at the same latitude
+    fn polar_hexagon(lat: f64, clockwise: bool) -> Vec<Vec3> {
+        let mut corners: Vec<Vec3> = (0..6)
+            .map(|i| from_lon_lat(f64::from(i) * 60.0 - 150.0, lat))
+            .collect();
+        if clockwise {
+            corners.reverse();
+        }
+        corners
+    }
+
+    fn rings(geometry: &Value) -> Vec<Vec<[f64; 2]>> {
+        let coordinates = &geometry["coordinates"];
+        let polygons = match geometry["type"].as_str() {
+            Some("Polygon") => vec![coordinates.clone()],
+            Some("MultiPolygon") => coordinates.as_array().unwrap().clone(),
+            other => panic!("not a polygon: {other:?}"),
+        };
+        polygons
+            .iter()
+            .flat_map(|polygon| polygon.as_array().unwrap().clone())
+            .map(|ring| serde_json::from_value(ring).unwrap())
+            .collect()
+    }
+
+    /// Twice the signed area of a closed ring, positive when it runs counter-clockwise
+    fn twice_area(ring: &[[f64; 2]]) -> f64 {
+        ring.windows(2)
+            .map(|pair| pair[0][0] * pair[1][1] - pair[1][0] * pair[0][1])
+            .sum()
+    }
+
+    fn assert_valid(ring: &[[f64; 2]]) {
+        assert_eq!(ring.first(), ring.last(), "ring isn't closed");
+        assert!(twice_area(ring) > 0.0, "ring runs clockwise");
+        for [lon, lat] in ring {
+            assert!(
+                (-180.0..=180.0).contains(lon),
+                "longitude {lon} off the map"
+            );
+            assert!((-90.0..=90.0).contains(lat), "latitude {lat} off the map");
+        }
+    }
+
+    #[test]
+    fn plain_face_is_one_polygon() {
+        for clockwise in [false, true] {
+            let geometry = face_geometry(&hexagon(10.0, 20.0, 1.0, clockwise));
+            assert_eq!(geometry["type"], "Polygon");
+            let rings = rings(&geometry);
+            assert_eq!(rings.len(), 1);
+            assert_eq!(rings[0].len(), 7);
+            assert_valid(&rings[0]);
+        }
+    }
+
+    #[test]
+    fn face_on_the_antimeridian_is_split_in_two() {
+        for clockwise in [false, true] {
+            let geometry = face_geometry(&hexagon(180.0, 5.0, 1.0, clockwise));
+            assert_eq!(geometry["type"], "MultiPolygon");
+            let rings = rings(&geometry);
+            assert_eq!(rings.len(), 2);
+            for ring in &rings {
+                assert_valid(ring);
+            }
+            // one half hugs each edge of the map
+            let west = rings[0].iter().all(|[lon, _]| *lon >= 178.0);
+            let east = rings[1].iter().all(|[lon, _]| *lon <= -178.0);
+            assert!(west && east, "halves in the wrong places: {rings:?}");
+            // the halves meet along the antimeridian
+            let cut_lats = |ring: &[[f64; 2]], edge: f64| {
+                let mut lats: Vec<f64> = ring
+                    .iter()
+                    .filter(|[lon, _]| (lon - edge).abs() < 1e-9)
+                    .map(|[_, lat]| *lat)
+                    .collect();
+                lats.sort_by(f64::total_cmp);
+                lats.dedup();
+                lats
+            };
+            let (west_cut, east_cut) = (cut_lats(&rings[0], 180.0), cut_lats(&rings[1], -180.0));
+            assert_eq!(west_cut.len(), 2);
+            assert!(
+                west_cut
+                    .iter()
+                    .zip(&east_cut)
+                    .all(|(a, b)| (a - b).abs() < 1e-9)
+            );
+        }
+    }
+
+    #[test]
+    fn faces_around_the_poles_become_caps() {
+        for (lat, pole) in [(89.0, 90.0), (-89.0, -90.0)] {
+            for clockwise in [false, true] {
+                let geometry = face_geometry(&polar_hexagon(lat, clockwise));
+                assert_eq!(geometry["type"], "Polygon");
+                let rings = rings(&geometry);
+                assert_eq!(rings.len(), 1);
+                let ring = &rings[0];
+                assert_valid(ring);
+                // the cap runs right across the map and up to its own pole, whatever the winding
+                assert!(ring.contains(&[-180.0, pole]));
+                assert!(ring.contains(&[180.0, pole]));
+                assert!(
+                    ring.iter()
+                        .all(|[_, p]| (p - lat).abs() <= (pole - lat).abs() + 1e-9)
+                );
+            }
+        }
+    }
+}
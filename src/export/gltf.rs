@@ -0,0 +1,275 @@
+use bevy::prelude::*;
+use serde_json::{Value, json};
+use std::{fs, path::PathBuf};
+
+use crate::elevation::Elevation;
+use crate::map_mode::{FaceColourQuery, MapMode, boundary_colour};
+use crate::relief::ReliefSettings;
+use crate::sea_level::SeaLevel;
+use crate::setup::{
+    FaceNeighbours, PlatePalette, build_face_positions, build_outline, side_floors,
+};
+
+/// Write the globe as a single binary glTF file, one mesh with a triangle primitive for the faces,
+/// optionally a line primitive for their outlines and, in modes that show them, one for the
+/// plate boundaries
+#[derive(Event)]
+pub struct ExportGltf {
+    pub path: PathBuf,
+    pub mode: MapMode,
+    /// Displace faces by their elevation the same way the relief view does, when enabled
+    pub relief: ReliefSettings,
+    pub outlines: bool,
+}
+
+// numbers the glTF spec uses for types and targets
+const FLOAT: u32 = 5126;
+const ARRAY_BUFFER: u32 = 34962;
+const TRIANGLES: u32 = 4;
+const LINES: u32 = 1;
+
+const GLB_MAGIC: u32 = 0x4654_6C67;
+const GLB_VERSION: u32 = 2;
+const CHUNK_JSON: u32 = 0x4E4F_534A;
+const CHUNK_BIN: u32 = 0x004E_4942;
+
+/// Boundary lines sit just above the outlines so they win where the two overlap
+const BOUNDARY_LIFT: f32 = 1.0002;
+
+pub(super) fn export_gltf(
+    export: On<ExportGltf>,
+    palette: Res<PlatePalette>,
+    sea_level: Res<SeaLevel>,
+    query_faces: Query<(Entity, FaceColourQuery)>,
+    q_neighbours: Query<&FaceNeighbours>,
+) {
+    let boundaries = if export.mode.shows_boundaries() {
+        boundary_lines(&query_faces, export.relief)
+    } else {
+        Vec::new()
+    };
+
+    let mut faces: Vec<_> = query_faces.iter().collect();
+    faces.sort_unstable_by_key(|(_, face)| face.face.index);
+
+    let radius_of = |elevation: Option<&Elevation>| {
+        export
+            .relief
+            .radius(elevation.map_or(0.0, |elevation| elevation.0))
+    };
+    // walls only go down to the face across each side, the one at the same height needs none
+    let floors_of = |entity_id: Entity, radius: f32| {
+        let Ok(face_neighbours) = q_neighbours.get(entity_id) else {
+            return Vec::new();
+        };
+        let neighbours = face_neighbours.iter().filter_map(|neighbour| {
+            let (_, neighbour) = query_faces.get(*neighbour).ok()?;
+            Some((neighbour.face, radius_of(neighbour.elevation)))
+        });
+        match query_faces.get(entity_id) {
+            Ok((_, face)) => side_floors(face.face, radius, neighbours),
+            Err(_) => Vec::new(),
+        }
+    };
+
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut colours: Vec<[f32; 4]> = Vec::new();
+    let mut outline: Vec<[f32; 3]> = Vec::new();
+
+    for (entity_id, face) in faces {
+        let colour = export.mode.colour(&palette, *sea_level, &face);
+        let vertices = &face.face.vertices;
+
+        let radius = radius_of(face.elevation);
+        let face_positions = build_face_positions(vertices, radius, &floors_of(entity_id, radius));
+        colours.extend(std::iter::repeat_n(
+            LinearRgba::from(colour).to_f32_array(),
+            face_positions.len(),
+        ));
+        positions.extend(face_positions);
+
+        if export.outlines {
+            outline.extend(build_outline(vertices, radius));
+        }
+    }
+
+    let glb = build_glb(&positions, &colours, &outline, &boundaries);
+    match fs::write(&export.path, glb) {
+        Ok(()) => info!("Wrote glTF to {}", export.path.display()),
+        Err(e) => error!("Could not write glTF to {}: {e}", export.path.display()),
+    }
+}
+
+/// Every side between two plates as a pair of line ends, each end with the colour of the line,
+/// raised to the higher of the two faces either side
+fn boundary_lines(
+    query_faces: &Query<(Entity, FaceColourQuery)>,
+    relief: ReliefSettings,
+) -> Vec<([f32; 3], [f32; 4])> {
+    let height = |elevation: Option<&Elevation>| elevation.map_or(0.0, |elevation| elevation.0);
+    let mut lines = Vec::new();
+    for (entity_id, face) in query_faces {
+        let Some(boundary) = face.boundary else {
+            continue;
+        };
+        for side in boundary.own_sides(entity_id) {
+            let Ok((_, across)) = query_faces.get(side.across) else {
+                continue;
+            };
+            let radius =
+                relief.radius(height(face.elevation).max(height(across.elevation))) * BOUNDARY_LIFT;
+            let colour = LinearRgba::from(boundary_colour(side)).to_f32_array();
+            lines.push(((side.ends.0 * radius).to_array(), colour));
+            lines.push(((side.ends.1 * radius).to_array(), colour));
+        }
+    }
+    lines
+}
+
+/// Lay the vertex data out in one binary buffer, describe it in the JSON chunk and wrap both up as a .glb
+fn build_glb(
+    positions: &[[f32; 3]],
+    colours: &[[f32; 4]],
+    outline: &[[f32; 3]],
+    boundaries: &[([f32; 3], [f32; 4])],
+) -> Vec<u8> {
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut buffer_views = Vec::new();
+    let mut accessors = Vec::new();
+
+    // every view starts on a 4 byte boundary, which f32 data always keeps us on
+    let mut add_accessor =
+        |data: &[f32], count: usize, kind: &str, bounds: Option<(Value, Value)>| {
+            let offset = buffer.len();
+            buffer.extend(data.iter().flat_map(|x| x.to_le_bytes()));
+            buffer_views.push(json!({
+                "buffer": 0,
+                "byteOffset": offset,
+                "byteLength": buffer.len() - offset,
+                "target": ARRAY_BUFFER,
+            }));
+            let mut accessor = json!({
+                "bufferView": buffer_views.len() - 1,
+                "componentType": FLOAT,
+                "count": count,
+                "type": kind,
+            });
+            if let Some((min, max)) = bounds {
+                accessor["min"] = min;
+                accessor["max"] = max;
+            }
+            accessors.push(accessor);
+            accessors.len() - 1
+        };
+
+    let position_accessor = add_accessor(
+        positions.as_flattened(),
+        positions.len(),
+        "VEC3",
+        Some(bounds(positions)),
+    );
+    let colour_accessor = add_accessor(colours.as_flattened(), colours.len(), "VEC4", None);
+
+    let mut primitives = vec![json!({
+        "attributes": {
+            "POSITION": position_accessor,
+            "COLOR_0": colour_accessor,
+        },
+        "material": 0,
+        "mode": TRIANGLES,
+    })];
+    if !outline.is_empty() {
+        let outline_accessor = add_accessor(
+            outline.as_flattened(),
+            outline.len(),
+            "VEC3",
+            Some(bounds(outline)),
+        );
+        primitives.push(json!({
+            "attributes": { "POSITION": outline_accessor },
+            "material": 1,
+            "mode": LINES,
+        }));
+    }
+    if !boundaries.is_empty() {
+        let (ends, end_colours): (Vec<[f32; 3]>, Vec<[f32; 4]>) =
+            boundaries.iter().copied().unzip();
+        let boundary_accessor =
+            add_accessor(ends.as_flattened(), ends.len(), "VEC3", Some(bounds(&ends)));
+        let boundary_colour_accessor =
+            add_accessor(end_colours.as_flattened(), end_colours.len(), "VEC4", None);
+        primitives.push(json!({
+            "attributes": {
+                "POSITION": boundary_accessor,
+                "COLOR_0": boundary_colour_accessor,
+            },
+            "material": 0,
+            "mode": LINES,
+        }));
+    }
+
+    let document = json!({
+        "asset": { "version": "2.0", "generator": "hex_globe" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [{ "mesh": 0, "name": "globe" }],
+        "meshes": [{ "name": "globe", "primitives": primitives }],
+        // vertex colours are multiplied by the base colour, so the faces get a white one
+        "materials": [
+            {
+                "name": "faces",
+                "pbrMetallicRoughness": {
+                    "baseColorFactor": [1.0, 1.0, 1.0, 1.0],
+                    "metallicFactor": 0.0,
+                    "roughnessFactor": 1.0,
+                },
+            },
+            {
+                "name": "outlines",
+                "pbrMetallicRoughness": {
+                    "baseColorFactor": [0.0, 0.0, 0.0, 1.0],
+                    "metallicFactor": 0.0,
+                    "roughnessFactor": 1.0,
+                },
+            },
+        ],
+        "accessors": accessors,
+        "bufferViews": buffer_views,
+        "buffers": [{ "byteLength": buffer.len() }],
+    });
+
+    wrap_glb(&document, buffer)
+}
+
+/// Put the JSON and binary chunks together behind the .glb header
+fn wrap_glb(document: &Value, mut buffer: Vec<u8>) -> Vec<u8> {
+    // both chunks have to be padded to 4 bytes, JSON with spaces and binary with zeros
+    let mut json_chunk = document.to_string().into_bytes();
+    json_chunk.resize(json_chunk.len().next_multiple_of(4), b' ');
+    buffer.resize(buffer.len().next_multiple_of(4), 0);
+
+    let total_length = 12 + 8 + json_chunk.len() + 8 + buffer.len();
+    let mut glb = Vec::with_capacity(total_length);
+    for word in [GLB_MAGIC, GLB_VERSION, total_length as u32] {
+        glb.extend(word.to_le_bytes());
+    }
+    glb.extend((json_chunk.len() as u32).to_le_bytes());
+    glb.extend(CHUNK_JSON.to_le_bytes());
+    glb.extend(json_chunk);
+    glb.extend((buffer.len() as u32).to_le_bytes());
+    glb.extend(CHUNK_BIN.to_le_bytes());
+    glb.extend(buffer);
+    glb
+}
+
+/// glTF insists on the min and max of every position accessor
+fn bounds(points: &[[f32; 3]]) -> (Value, Value) {
+    let (min, max) =
+        points
+            .iter()
+            .fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(min, max), point| {
+                let point = Vec3::from_array(*point);
+                (min.min(point), max.max(point))
+            });
+    (json!(min.to_array()), json!(max.to_array()))
+}
@@ -0,0 +1,154 @@
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use clap::ValueEnum;
+use image::{ImageFormat, Rgba, RgbaImage};
+use std::f64::consts::PI;
+use std::path::PathBuf;
+
+use super::from_lon_lat;
+use crate::map_mode::{FaceColourQuery, FaceColourQueryItem, MapMode, boundary_colour};
+use crate::sea_level::SeaLevel;
+use crate::setup::{FaceNeighbours, PlatePalette, nearest_face};
+
+/// How the globe is flattened onto the image
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum MapProjection {
+    /// Longitude and latitude straight onto x and y, twice as wide as it is tall
+    #[default]
+    Equirectangular,
+    /// Equal area ellipse, anything outside it is left transparent
+    Mollweide,
+}
+
+/// Width of the boundary lines, in pixels of latitude
+const BOUNDARY_WIDTH: f32 = 1.5;
+
+/// Rasterise the globe into a PNG, `width` pixels across and half that high
+#[derive(Event)]
+pub struct ExportMapImage {
+    pub path: PathBuf,
+    pub width: u32,
+    pub projection: MapProjection,
+    pub mode: MapMode,
+}
+
+impl MapProjection {
+    /// Longitude and latitude in degrees under the centre of a pixel, `None` if it's off the map
+    fn lon_lat(self, x: u32, y: u32, width: u32, height: u32) -> Option<(f64, f64)> {
+        // -1..1 across and down the image
+        let u = (f64::from(x) + 0.5) / f64::from(width) * 2.0 - 1.0;
+        let v = 1.0 - (f64::from(y) + 0.5) / f64::from(height) * 2.0;
+
+        match self {
+            MapProjection::Equirectangular => Some((u * 180.0, v * 90.0)),
+            MapProjection::Mollweide => {
+                if u * u + v * v > 1.0 {
+                    return None;
+                }
+                let theta = v.asin();
+                let lat = ((2.0 * theta + (2.0 * theta).sin()) / PI).asin();
+                // u and v are x / 2√2 and y / √2 in the usual Mollweide formulas
+                let lon = PI * u / theta.cos();
+                Some((lon.to_degrees(), lat.to_degrees()))
+            }
+        }
+    }
+}
+
+pub(super) fn export_map_image(
+    export: On<ExportMapImage>,
+    palette: Res<PlatePalette>,
+    sea_level: Res<SeaLevel>,
+    query_faces: Query<(Entity, &FaceNeighbours, FaceColourQuery)>,
+) {
+    // flatten the faces into plain arrays so looking up a pixel doesn't go through the ECS
+    let slots: HashMap<Entity, usize> = query_faces
+        .iter()
+        .enumerate()
+        .map(|(i, (entity_id, ..))| (entity_id, i))
+        .collect();
+    let mut centres = Vec::with_capacity(slots.len());
+    let mut neighbours = Vec::with_capacity(slots.len());
+    let mut colours = Vec::with_capacity(slots.len());
+    // the centre of the face across each boundary side, and the colour of the line along it
+    let mut boundaries = Vec::with_capacity(slots.len());
+    for (_, face_neighbours, face) in query_faces.iter() {
+        let colour = export.mode.colour(&palette, *sea_level, &face);
+        boundaries.push(if export.mode.shows_boundaries() {
+            boundary_sides(&query_faces, &face)
+        } else {
+            Vec::new()
+        });
+
+        centres.push(face.face.centre_pos.normalize());
+        neighbours.push(
+            face_neighbours
+                .iter()
+                .filter_map(|entity_id| slots.get(entity_id).copied())
+                .collect::<Vec<_>>(),
+        );
+        colours.push(Rgba(colour.to_srgba().to_u8_array()));
+    }
+
+    if centres.is_empty() {
+        error!("No faces to draw into {}", export.path.display());
+        return;
+    }
+
+    let width = export.width.max(2);
+    let height = width / 2;
+    let mut image = RgbaImage::new(width, height);
+    #[allow(clippy::cast_precision_loss)]
+    let half_width = BOUNDARY_WIDTH / 2.0 * std::f32::consts::PI / height as f32;
+
+    // neighbouring pixels land in the same or a neighbouring face,
+    // so start each search from the last face we found
+    let mut current = 0;
+    for y in 0..height {
+        for x in 0..width {
+            let Some((lon, lat)) = export.projection.lon_lat(x, y, width, height) else {
+                continue;
+            };
+            let point = from_lon_lat(lon, lat);
+            current = nearest_face(point, current, &centres, &neighbours);
+            // the side between two faces is where they're equally far away, the plane through the
+            // middle of the globe with the difference of their centres as its normal
+            let centre = centres[current];
+            let line = boundaries[current].iter().find(|(across, _)| {
+                let normal = centre - *across;
+                point.dot(normal).abs() < half_width * normal.length()
+            });
+            image.put_pixel(x, y, line.map_or(colours[current], |(_, colour)| *colour));
+        }
+    }
+
+    match image.save_with_format(&export.path, ImageFormat::Png) {
+        Ok(()) => info!("Wrote map image to {}", export.path.display()),
+        Err(e) => error!(
+            "Could not write map image to {}: {e}",
+            export.path.display()
+        ),
+    }
+}
+
+/// The centre of the face across each of `face`'s boundary sides and the colour of the line there
+fn boundary_sides(
+    query_faces: &Query<(Entity, &FaceNeighbours, FaceColourQuery)>,
+    face: &FaceColourQueryItem,
+) -> Vec<(Vec3, Rgba<u8>)> {
+    let Some(boundary) = face.boundary else {
+        return Vec::new();
+    };
+    boundary
+        .sides
+        .iter()
+        .filter_map(|side| {
+            let (_, _, across) = query_faces.get(side.across).ok()?;
+            let colour = boundary_colour(side);
+            Some((
+                across.face.centre_pos.normalize(),
+                Rgba(colour.to_srgba().to_u8_array()),
+            ))
+        })
+        .collect()
+}
@@ -0,0 +1,352 @@
+// biases on how the flood fill grows plates, so they don't all come out as round blobs
+// of the same size, and a histogram of the sizes they came out as
+
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{GrowthStrategy, WorldGenConfig};
+use crate::noise::Noise;
+use crate::states::WorldGenState;
+use crate::worldgen::{Plate, random_unit_vector};
+
+/// Octaves summed for noise weighted growth
+const NOISE_OCTAVES: u32 = 3;
+/// Least chance a pick is ever taken, however hard the biases lean against it, so strong biases
+/// slow a plate down rather than stall the flood fill
+const MIN_WEIGHT: f32 = 0.02;
+const BAR_COLOUR: Color = Color::srgb(0.8, 0.8, 0.8);
+const GROWN_BAR_COLOUR: Color = Color::srgb(0.45, 0.45, 0.45);
+/// Width of the bar for the fullest bin
+const MAX_BAR_WIDTH: f32 = 200.0;
+
+/// What each plate was dealt for each growth strategy in the config
+#[derive(Resource, Clone, Default, Serialize, Deserialize)]
+pub struct GrowthBias(Vec<PlateBias>);
+
+#[derive(Clone, Serialize, Deserialize)]
+enum PlateBias {
+    /// Each plate's growth rate, the fastest plate's is 1
+    Rates(Vec<f32>),
+    /// The axis of each plate's great circle
+    Directions { axes: Vec<Vec3>, strength: f32 },
+    /// Every plate gets its own field, seeded from this one
+    Noise {
+        seed: u64,
+        scale: f32,
+        strength: f32,
+    },
+}
+
+impl GrowthBias {
+    pub fn new(strategies: &[GrowthStrategy], n_plates: usize, rng: &mut impl Rng) -> Self {
+        Self(
+            strategies
+                .iter()
+                .map(|strategy| match *strategy {
+                    GrowthStrategy::PowerLaw { exponent } => {
+                        // pareto distributed, worked in logs so tiny exponents can't overflow
+                        let log_rates: Vec<f32> = (0..n_plates)
+                            .map(|_| -(1.0 - rng.random::<f32>()).ln() / exponent)
+                            .collect();
+                        let fastest = log_rates.iter().copied().fold(0.0, f32::max);
+                        PlateBias::Rates(
+                            log_rates
+                                .into_iter()
+                                .map(|log_rate| (log_rate - fastest).exp())
+                                .collect(),
+                        )
+                    }
+                    GrowthStrategy::Directional { strength } => PlateBias::Directions {
+                        axes: (0..n_plates).map(|_| random_unit_vector(rng)).collect(),
+                        strength,
+                    },
+                    GrowthStrategy::Noise { scale, strength } => PlateBias::Noise {
+                        seed: rng.random(),
+                        scale,
+                        strength,
+                    },
+                })
+                .collect(),
+        )
+    }
+
+    /// Without any biases every pick is taken, so there's nothing to draw for
+    pub fn is_uniform(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Chance that `plate` claims the face centred at `to` when it picks it from the face at `from`,
+    /// between `MIN_WEIGHT` and 1. Each bias gives 1 where it favours the pick most
+    pub fn weight(&self, plate: usize, from: Vec3, to: Vec3) -> f32 {
+        self.0
+            .iter()
+            .map(|bias| match bias {
+                PlateBias::Rates(rates) => rates[plate],
+                PlateBias::Directions { axes, strength } => {
+                    let along = axes[plate].cross(from).normalize_or_zero();
+                    let step = (to - from).normalize_or_zero();
+                    (strength * (step.dot(along).abs() - 1.0)).exp()
+                }
+                PlateBias::Noise {
+                    seed,
+                    scale,
+                    strength,
+                } => {
+                    let noise = Noise::new(seed.wrapping_add((plate as u64) << 8));
+                    // from -1 to 1 onto 0 to 1, fbm hardly ever reaches its ends
+                    let height = 0.5 * (noise.fbm(to * *scale, NOISE_OCTAVES) + 1.0);
+                    (strength * (height - 1.0)).exp()
+                }
+            })
+            .product::<f32>()
+            .max(MIN_WEIGHT)
+    }
+}
+
+/// How many faces each plate has
+pub struct PlateSizes(Vec<usize>);
+
+impl PlateSizes {
+    pub fn count<'a>(plates: impl IntoIterator<Item = &'a Plate>, n_plates: usize) -> Self {
+        let mut sizes = vec![0; n_plates];
+        for plate in plates {
+            if plate.0 >= sizes.len() {
+                sizes.resize(plate.0 + 1, 0);
+            }
+            sizes[plate.0] += 1;
+        }
+        Self(sizes)
+    }
+
+    /// Number of plates in each doubling bin of size, 1, 2-3, 4-7 faces and so on,
+    /// as (smallest size in the bin, number of plates), empty plates aren't counted
+    pub fn histogram(&self) -> Vec<(usize, usize)> {
+        let largest = self.0.iter().copied().max().unwrap_or(0);
+        let n_bins = (usize::BITS - largest.leading_zeros()) as usize;
+        let mut bins: Vec<(usize, usize)> = (0..n_bins).map(|bin| (1 << bin, 0)).collect();
+        for size in self.0.iter().filter(|size| **size > 0) {
+            bins[size.ilog2() as usize].1 += 1;
+        }
+        bins
+    }
+
+    fn summary(&self) -> String {
+        let sizes = self.0.iter().filter(|size| **size > 0);
+        format!(
+            "{} plates, {} to {} faces",
+            sizes.clone().count(),
+            sizes.clone().min().unwrap_or(&0),
+            sizes.max().unwrap_or(&0),
+        )
+    }
+}
+
+/// Text for a bin, `smallest` is always a power of two
+fn bin_label(smallest: usize) -> String {
+    if smallest == 1 {
+        "1".to_string()
+    } else {
+        format!("{smallest}-{}", smallest * 2 - 1)
+    }
+}
+
+/// Plate sizes as the plates finished growing, before any small ones were merged away
+#[derive(Resource)]
+pub struct GrownPlateSizes(PlateSizes);
+
+/// Write a plate size histogram to the log, so it can be checked without a window
+fn log_sizes(title: &str, sizes: &PlateSizes) {
+    let mut lines = vec![format!("{title}: {}", sizes.summary())];
+    for (smallest, count) in sizes.histogram() {
+        lines.push(format!(
+            "{:>11} faces: {:<3} {}",
+            bin_label(smallest),
+            count,
+            "#".repeat(count)
+        ));
+    }
+    info!("{}", lines.join("\n"));
+}
+
+/// Keep and log the sizes the plates grew to, before merging changes them
+pub(crate) fn record_grown_sizes(
+    mut commands: Commands,
+    config: Res<WorldGenConfig>,
+    q_plates: Query<&Plate>,
+) {
+    let sizes = PlateSizes::count(q_plates.iter(), config.n_plates);
+    log_sizes("Plate sizes as grown", &sizes);
+    commands.insert_resource(GrownPlateSizes(sizes));
+}
+
+/// The grown sizes only belong to the plates just merged, a rewind or a load mustn't show them
+pub(crate) fn forget_grown_sizes(mut commands: Commands) {
+    commands.remove_resource::<GrownPlateSizes>();
+}
+
+/// Log the sizes again once small plates are merged away, if any could have been
+pub(crate) fn log_plate_sizes(config: Res<WorldGenConfig>, q_plates: Query<&Plate>) {
+    if config.min_plate_size > 0 {
+        let sizes = PlateSizes::count(q_plates.iter(), config.n_plates);
+        log_sizes("Plate sizes after merging", &sizes);
+    }
+}
+
+#[derive(Component)]
+struct PlateSizesUi;
+
+fn setup_plate_sizes_ui(
+    mut commands: Commands,
+    config: Res<WorldGenConfig>,
+    grown: Option<Res<GrownPlateSizes>>,
+    q_plates: Query<&Plate>,
+) {
+    let sizes = PlateSizes::count(q_plates.iter(), config.n_plates);
+    let histogram = sizes.histogram();
+    // the sizes as grown are only worth showing alongside if merging changed them
+    let grown = grown
+        .map(|grown| (grown.0.summary(), grown.0.histogram()))
+        .filter(|(_, grown)| *grown != histogram);
+
+    // merging can empty the smallest bins and fill bigger ones, so show every bin either has
+    let count_in = |bins: &[(usize, usize)], bin: usize| bins.get(bin).map_or(0, |(_, n)| *n);
+    let n_bins = histogram
+        .len()
+        .max(grown.as_ref().map_or(0, |(_, bins)| bins.len()));
+    let rows: Vec<(usize, usize, Option<usize>)> = (0..n_bins)
+        .map(|bin| {
+            let grown_count = grown.as_ref().map(|(_, bins)| count_in(bins, bin));
+            (1 << bin, count_in(&histogram, bin), grown_count)
+        })
+        .collect();
+    let fullest = rows
+        .iter()
+        .map(|(_, count, grown_count)| (*count).max(grown_count.unwrap_or(0)))
+        .max()
+        .unwrap_or(0);
+    #[allow(clippy::cast_precision_loss)]
+    let width = |count: usize| MAX_BAR_WIDTH * count as f32 / fullest.max(1) as f32;
+
+    commands
+        .spawn((
+            // under the seed, the rest of the left side belongs to the relief and motion controls
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(35.0),
+                left: Val::Px(5.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(2.0),
+                ..default()
+            },
+            PlateSizesUi,
+        ))
+        .with_children(|parent| {
+            parent.spawn(Text::new(format!("Plate sizes: {}", sizes.summary())));
+            if let Some((summary, _)) = &grown {
+                parent.spawn(Text::new(format!("As grown: {summary}")));
+            }
+            for (smallest, count, grown_count) in rows {
+                parent
+                    .spawn(Node {
+                        column_gap: Val::Px(5.0),
+                        align_items: AlignItems::Center,
+                        ..default()
+                    })
+                    .with_children(|row| {
+                        row.spawn((
+                            Text::new(format!("{} faces", bin_label(smallest))),
+                            TextLayout::new_with_justify(Justify::Right),
+                            Node {
+                                width: Val::Px(110.0),
+                                ..default()
+                            },
+                        ));
+                        // the merged bar, with a thin one under it for how many grew that size
+                        row.spawn(Node {
+                            flex_direction: FlexDirection::Column,
+                            row_gap: Val::Px(1.0),
+                            ..default()
+                        })
+                        .with_children(|bars| {
+                            bars.spawn((
+                                Node {
+                                    width: Val::Px(width(count)),
+                                    height: Val::Px(12.0),
+                                    ..default()
+                                },
+                                BackgroundColor(BAR_COLOUR),
+                            ));
+                            if let Some(grown_count) = grown_count {
+                                bars.spawn((
+                                    Node {
+                                        width: Val::Px(width(grown_count)),
+                                        height: Val::Px(3.0),
+                                        ..default()
+                                    },
+                                    BackgroundColor(GROWN_BAR_COLOUR),
+                                ));
+                            }
+                        });
+                        row.spawn(Text::new(match grown_count {
+                            Some(grown_count) if grown_count != count => {
+                                format!("{count} (grew {grown_count})")
+                            }
+                            _ => count.to_string(),
+                        }));
+                    });
+            }
+        });
+}
+
+/// Shows the plate size histogram while waiting after the plates are grown
+pub struct PlateSizesUiPlugin;
+
+impl Plugin for PlateSizesUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(WorldGenState::FinishedPlates), setup_plate_sizes_ui)
+            .add_systems(
+                OnExit(WorldGenState::FinishedPlates),
+                crate::ui::cleanup_ui::<PlateSizesUi>,
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn strong_biases_slow_plates_down_without_stopping_them() {
+        let strategies = [
+            GrowthStrategy::PowerLaw { exponent: 0.01 },
+            GrowthStrategy::Directional { strength: 50.0 },
+            GrowthStrategy::Noise {
+                scale: 3.0,
+                strength: 50.0,
+            },
+        ];
+        let bias = GrowthBias::new(&strategies, 8, &mut ChaCha8Rng::seed_from_u64(3));
+        let from = Vec3::X;
+        for plate in 0..8 {
+            for step in [Vec3::Y, Vec3::Z, Vec3::new(0.0, 1.0, 1.0)] {
+                let to = (from + 0.05 * step).normalize();
+                let weight = bias.weight(plate, from, to);
+                assert!((MIN_WEIGHT..=1.0).contains(&weight), "weight {weight}");
+            }
+        }
+    }
+
+    #[test]
+    fn the_fastest_plate_takes_every_pick() {
+        let strategies = [GrowthStrategy::PowerLaw { exponent: 2.0 }];
+        let bias = GrowthBias::new(&strategies, 5, &mut ChaCha8Rng::seed_from_u64(9));
+        let (from, to) = (Vec3::X, Vec3::new(1.0, 0.05, 0.0).normalize());
+        let fastest = (0..5)
+            .map(|plate| bias.weight(plate, from, to))
+            .fold(0.0, f32::max);
+        assert!((fastest - 1.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,204 @@
+// run world generation without a window, stepping through every stage automatically
+
+use bevy::log::LogPlugin;
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+use bevy::time::TimeUpdateStrategy;
+use serde::Serialize;
+use std::{fs, time::Duration};
+
+use crate::cli::GenerateArgs;
+use crate::config::WorldGenConfig;
+use crate::crust::{CrustThickness, CrustType};
+use crate::elevation::Elevation;
+use crate::export::{ExportGeoJson, ExportGltf, ExportMapImage, ExportPlugin};
+use crate::plate_graph::PlateGraphPlugin;
+use crate::relief::ReliefSettings;
+use crate::save::{PendingLoad, SaveFile, SavePlugin, SaveWorld};
+use crate::sea_level::{SeaLevel, SeaLevelPlugin};
+use crate::seed::{SeedPlugin, WorldSeed};
+use crate::setup::{Face, SetupPlugin};
+use crate::states::{GameState, StatePlugin, WorldGenState};
+use crate::worldgen::{
+    BoundaryKind, FacePlateVelocity, Land, Plate, PlateBoundary, WorldGenPlugin,
+};
+
+/// Where to write the world, and any exports of it, once generation is done
+#[derive(Resource, Deref)]
+struct Outputs(GenerateArgs);
+
+#[derive(Serialize)]
+struct GeneratedWorld {
+    seed: u64,
+    config: WorldGenConfig,
+    /// Faces above this are land, in km
+    sea_level: f32,
+    faces: Vec<GeneratedFace>,
+}
+
+#[derive(Serialize)]
+struct GeneratedFace {
+    index: usize,
+    centre: [f32; 3],
+    plate: usize,
+    boundary: bool,
+    boundary_kind: Option<BoundaryKind>,
+    boundary_magnitude: Option<f32>,
+    land: bool,
+    crust: CrustType,
+    crust_thickness: f32,
+    elevation: f32,
+    velocity: [f32; 3],
+}
+
+pub fn run(
+    seed: WorldSeed,
+    config: WorldGenConfig,
+    save: Option<SaveFile>,
+    args: GenerateArgs,
+) -> AppExit {
+    let tick = Duration::from_millis(crate::TICK_RATE);
+
+    App::new()
+        .add_plugins(MinimalPlugins)
+        .add_plugins(LogPlugin::default())
+        .add_plugins(StatesPlugin)
+        .insert_resource(Time::<Fixed>::from_duration(tick))
+        // advance exactly one fixed tick per update so we never wait on the wall clock
+        .insert_resource(TimeUpdateStrategy::ManualDuration(tick))
+        .insert_resource(seed)
+        .insert_resource(config)
+        .insert_resource(PendingLoad(save))
+        .insert_resource(Outputs(args))
+        .add_plugins(SeedPlugin)
+        .add_plugins(SetupPlugin)
+        .add_plugins(WorldGenPlugin)
+        .add_plugins(PlateGraphPlugin)
+        .add_plugins(SeaLevelPlugin)
+        .add_plugins(StatePlugin)
+        .add_plugins(ExportPlugin)
+        .add_plugins(SavePlugin)
+        .add_systems(Update, auto_advance.run_if(in_state(GameState::WorldGen)))
+        .add_systems(OnEnter(WorldGenState::GenElevation), override_land_target)
+        .add_systems(OnEnter(GameState::Simulation), write_world)
+        .run()
+}
+
+/// Does what pressing space does in the windowed app
+fn auto_advance(
+    state: Res<State<WorldGenState>>,
+    mut gen_state: ResMut<NextState<WorldGenState>>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    match state.get() {
+        WorldGenState::FinishedPlates => gen_state.set(WorldGenState::AssignPlateBoundaries),
+        WorldGenState::FinishedPlateBoundaries => gen_state.set(WorldGenState::GenContinents),
+        WorldGenState::FinishedContinents => gen_state.set(WorldGenState::GenPlateVelocities),
+        WorldGenState::FinishedPlateVelocities => gen_state.set(WorldGenState::ClassifyBoundaries),
+        WorldGenState::FinishedBoundaryClassification => {
+            gen_state.set(WorldGenState::GenElevation);
+        }
+        WorldGenState::JustChill => {
+            gen_state.set(WorldGenState::Finished);
+            game_state.set(GameState::Simulation);
+        }
+        _ => {}
+    }
+}
+
+/// Swap in the requested share of land before the sea level is fitted to the new heights
+fn override_land_target(outputs: Res<Outputs>, mut sea_level: ResMut<SeaLevel>) {
+    if let Some(target) = outputs.land_target {
+        sea_level.target_land = target;
+    }
+}
+
+fn write_world(
+    mut commands: Commands,
+    seed: Res<WorldSeed>,
+    config: Res<WorldGenConfig>,
+    sea_level: Res<SeaLevel>,
+    outputs: Res<Outputs>,
+    query_faces: Query<(
+        &Face,
+        &Plate,
+        Option<&PlateBoundary>,
+        Has<Land>,
+        &CrustType,
+        &CrustThickness,
+        &Elevation,
+        &FacePlateVelocity,
+    )>,
+    mut exit: MessageWriter<AppExit>,
+) {
+    let mut faces: Vec<GeneratedFace> = query_faces
+        .iter()
+        .map(
+            |(face, plate, boundary, land, crust, thickness, elevation, velocity)| {
+                let class = boundary.and_then(PlateBoundary::class);
+                GeneratedFace {
+                    index: face.index,
+                    centre: face.centre_pos.to_array(),
+                    plate: plate.0,
+                    boundary: boundary.is_some(),
+                    boundary_kind: class.map(|class| class.kind),
+                    boundary_magnitude: class.map(|class| class.magnitude),
+                    land,
+                    crust: *crust,
+                    crust_thickness: thickness.0,
+                    elevation: elevation.0,
+                    velocity: velocity.velocity.to_array(),
+                }
+            },
+        )
+        .collect();
+    faces.sort_unstable_by_key(|face| face.index);
+
+    if let Some(path) = &outputs.save {
+        commands.trigger(SaveWorld { path: path.clone() });
+    }
+    if let Some(path) = &outputs.geojson {
+        commands.trigger(ExportGeoJson { path: path.clone() });
+    }
+    if let Some(path) = &outputs.png {
+        commands.trigger(ExportMapImage {
+            path: path.clone(),
+            width: outputs.png_width,
+            projection: outputs.png_projection,
+            mode: outputs.png_mode,
+        });
+    }
+    if let Some(path) = &outputs.glb {
+        commands.trigger(ExportGltf {
+            path: path.clone(),
+            mode: outputs.glb_mode,
+            relief: ReliefSettings {
+                enabled: outputs.glb_relief.is_some(),
+                exaggeration: outputs.glb_relief.unwrap_or(1.0),
+            },
+            outlines: outputs.glb_outlines,
+        });
+    }
+
+    let world = GeneratedWorld {
+        seed: seed.0,
+        config: config.clone(),
+        sea_level: sea_level.level,
+        faces,
+    };
+
+    let result = serde_json::to_string(&world)
+        .map_err(|e| e.to_string())
+        .and_then(|json| fs::write(&outputs.out, json).map_err(|e| e.to_string()));
+
+    match result {
+        Ok(()) => {
+            info!("Wrote world to {}", outputs.out.display());
+            exit.write(AppExit::Success);
+        }
+        Err(e) => {
+            error!("Could not write world to {}: {e}", outputs.out.display());
+            exit.write(AppExit::error());
+        }
+    }
+}
@@ -0,0 +1,392 @@
+// point at a face to see what it is, click it to pin a panel with everything we know about it
+
+use bevy::picking::pointer::PointerButton;
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+
+use crate::crust::{CrustThickness, CrustType};
+use crate::elevation::Elevation;
+use crate::export::to_lon_lat;
+use crate::plate_graph::PlateGraph;
+use crate::relief::ReliefSettings;
+use crate::removed::AnyRemoved;
+use crate::setup::{Face, FaceNeighbours, GlobeChunk, nearest_face};
+use crate::worldgen::{FacePlateVelocity, Land, Plate, PlateBoundary, Sea};
+
+/// A press that moves further than this before it's released was the camera being dragged
+const CLICK_TOLERANCE_PX: f32 = 4.0;
+
+const HOVER_COLOUR: Color = Color::srgb(1.0, 1.0, 0.2);
+const SELECTED_COLOUR: Color = Color::srgb(1.0, 0.2, 1.0);
+
+/// The face under the cursor
+#[derive(Resource, Default)]
+struct HoveredFace(Option<Entity>);
+
+/// The face the inspect panel is showing
+#[derive(Resource, Default)]
+struct SelectedFace(Option<Entity>);
+
+/// Where the last press on the globe happened, to tell clicks from camera drags
+#[derive(Resource, Default)]
+struct PressPosition(Option<Vec2>);
+
+#[derive(Component)]
+struct TooltipUiText;
+
+#[derive(Component)]
+struct InspectPanelUiText;
+
+/// The faces laid out for `nearest_face` to walk, kept so the pointer moving doesn't have to
+/// gather every face again
+#[derive(Resource, Default)]
+struct FaceGraph {
+    entities: Vec<Entity>,
+    slots: HashMap<Entity, usize>,
+    centres: Vec<Vec3>,
+    neighbours: Vec<Vec<usize>>,
+}
+
+impl FaceGraph {
+    /// The face whose centre is closest to a point on (or near) the globe, walking across the
+    /// faces from `start`, usually the face hovered last and right next to the point
+    fn face_at(&self, point: Vec3, start: Option<Entity>) -> Option<Entity> {
+        if self.entities.is_empty() {
+            return None;
+        }
+        let start = start
+            .and_then(|entity_id| self.slots.get(&entity_id).copied())
+            .unwrap_or(0);
+        let slot = nearest_face(point, start, &self.centres, &self.neighbours);
+        Some(self.entities[slot])
+    }
+}
+
+/// Gather the faces again whenever the globe is built or rebuilt
+fn update_face_graph(
+    mut graph: ResMut<FaceGraph>,
+    query_faces: Query<(Entity, &Face, &FaceNeighbours)>,
+    changed_faces: Query<(), Changed<FaceNeighbours>>,
+    mut removed: RemovedComponents<Face>,
+) {
+    if changed_faces.is_empty() && !removed.any_removed() {
+        return;
+    }
+
+    let entities: Vec<Entity> = query_faces
+        .iter()
+        .map(|(entity_id, _, _)| entity_id)
+        .collect();
+    let slots: HashMap<Entity, usize> = entities
+        .iter()
+        .enumerate()
+        .map(|(slot, &entity_id)| (entity_id, slot))
+        .collect();
+    let (centres, neighbours) = query_faces
+        .iter()
+        .map(|(_, face, face_neighbours)| {
+            let neighbours = face_neighbours
+                .iter()
+                .filter_map(|entity_id| slots.get(entity_id).copied())
+                .collect::<Vec<_>>();
+            (face.centre_pos, neighbours)
+        })
+        .unzip();
+    *graph = FaceGraph {
+        entities,
+        slots,
+        centres,
+        neighbours,
+    };
+}
+
+fn hover_face(
+    event: On<Pointer<Move>>,
+    q_chunks: Query<(), With<GlobeChunk>>,
+    graph: Res<FaceGraph>,
+    mut hovered: ResMut<HoveredFace>,
+) {
+    if !q_chunks.contains(event.entity) {
+        return;
+    }
+    if let Some(position) = event.hit.position {
+        hovered.0 = graph.face_at(position, hovered.0);
+    }
+}
+
+fn unhover_face(
+    event: On<Pointer<Out>>,
+    q_chunks: Query<(), With<GlobeChunk>>,
+    mut hovered: ResMut<HoveredFace>,
+) {
+    if q_chunks.contains(event.entity) {
+        hovered.0 = None;
+    }
+}
+
+fn press_face(
+    event: On<Pointer<Press>>,
+    q_chunks: Query<(), With<GlobeChunk>>,
+    mut press: ResMut<PressPosition>,
+) {
+    if q_chunks.contains(event.entity) && event.button == PointerButton::Primary {
+        press.0 = Some(event.pointer_location.position);
+    }
+}
+
+/// Select the clicked face, unless the click was really the end of orbiting the camera
+fn click_face(
+    event: On<Pointer<Click>>,
+    q_chunks: Query<(), With<GlobeChunk>>,
+    graph: Res<FaceGraph>,
+    press: Res<PressPosition>,
+    hovered: Res<HoveredFace>,
+    mut selected: ResMut<SelectedFace>,
+) {
+    if !q_chunks.contains(event.entity) || event.button != PointerButton::Primary {
+        return;
+    }
+    let dragged = press
+        .0
+        .is_none_or(|start| start.distance(event.pointer_location.position) > CLICK_TOLERANCE_PX);
+    if dragged {
+        return;
+    }
+    if let Some(position) = event.hit.position {
+        selected.0 = graph.face_at(position, hovered.0);
+    }
+}
+
+fn handle_inspect_keys(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut selected: ResMut<SelectedFace>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        selected.0 = None;
+    }
+}
+
+/// Loading a save rebuilds the globe, so the faces we were looking at can disappear
+fn forget_despawned_faces(
+    query_faces: Query<(), With<Face>>,
+    mut hovered: ResMut<HoveredFace>,
+    mut selected: ResMut<SelectedFace>,
+) {
+    if hovered
+        .0
+        .is_some_and(|entity_id| !query_faces.contains(entity_id))
+    {
+        hovered.0 = None;
+    }
+    if selected
+        .0
+        .is_some_and(|entity_id| !query_faces.contains(entity_id))
+    {
+        selected.0 = None;
+    }
+}
+
+/// Outline the hovered and selected faces, lifted to match the relief view
+fn draw_highlights(
+    mut gizmos: Gizmos,
+    hovered: Res<HoveredFace>,
+    selected: Res<SelectedFace>,
+    relief: Res<ReliefSettings>,
+    query_faces: Query<(&Face, Option<&Elevation>)>,
+) {
+    for (entity_id, colour) in [(hovered.0, HOVER_COLOUR), (selected.0, SELECTED_COLOUR)] {
+        let Some((face, elevation)) =
+            entity_id.and_then(|entity_id| query_faces.get(entity_id).ok())
+        else {
+            continue;
+        };
+        let radius = relief.radius(elevation.map_or(0.0, |elevation| elevation.0)) * 1.0005;
+        let corners = face.vertices.iter().map(|vertex| *vertex * radius);
+        gizmos.linestrip(corners.clone().chain(corners.take(1)), colour);
+    }
+}
+
+fn setup_inspect_ui(mut commands: Commands) {
+    commands.spawn((
+        Text::new(""),
+        Node {
+            position_type: PositionType::Absolute,
+            padding: UiRect::all(Val::Px(4.0)),
+            display: Display::None,
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+        // the tooltip follows the cursor, it mustn't get in the way of what's under it
+        Pickable::IGNORE,
+        TooltipUiText,
+    ));
+    commands.spawn((
+        Text::new(""),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(90.0),
+            right: Val::Px(5.0),
+            padding: UiRect::all(Val::Px(6.0)),
+            max_width: Val::Px(320.0),
+            display: Display::None,
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+        InspectPanelUiText,
+    ));
+}
+
+fn update_tooltip(
+    hovered: Res<HoveredFace>,
+    window: Single<&Window>,
+    query_faces: Query<(&Face, Option<&Plate>)>,
+    mut tooltip: Single<(&mut Text, &mut Node), With<TooltipUiText>>,
+) {
+    let (text, node) = &mut *tooltip;
+    let face = hovered
+        .0
+        .and_then(|entity_id| query_faces.get(entity_id).ok());
+    let (Some((face, plate)), Some(cursor)) = (face, window.cursor_position()) else {
+        node.display = Display::None;
+        return;
+    };
+
+    // only touch the text when it changes so it isn't laid out again every frame
+    text.set_if_neq(Text::new(match plate {
+        Some(plate) => format!("Face {}, plate {}", face.index, plate.0),
+        None => format!("Face {}", face.index),
+    }));
+    node.display = Display::Flex;
+    node.left = Val::Px(cursor.x + 14.0);
+    node.top = Val::Px(cursor.y + 14.0);
+}
+
+fn update_inspect_panel(
+    selected: Res<SelectedFace>,
+    graph: Res<PlateGraph>,
+    query_faces: Query<(
+        &Face,
+        &FaceNeighbours,
+        Option<&Plate>,
+        Option<&PlateBoundary>,
+        Has<Land>,
+        Has<Sea>,
+        Option<&Elevation>,
+        Option<&FacePlateVelocity>,
+    )>,
+    q_crust: Query<(&CrustType, &CrustThickness)>,
+    q_indices: Query<&Face>,
+    mut panel: Single<(&mut Text, &mut Node), With<InspectPanelUiText>>,
+) {
+    let (text, node) = &mut *panel;
+    let Some((face, neighbours, plate, boundary, land, sea, elevation, velocity)) = selected
+        .0
+        .and_then(|entity_id| query_faces.get(entity_id).ok())
+    else {
+        node.display = Display::None;
+        return;
+    };
+
+    let (lon, lat) = to_lon_lat(face.centre_pos);
+    let mut lines = vec![
+        format!("Face {}", face.index),
+        format!("Lat {lat:.2}, lon {lon:.2}"),
+        match plate {
+            Some(plate) => format!("Plate {}", plate.0),
+            None => "No plate yet".to_string(),
+        },
+    ];
+    if let Some(node) = plate.and_then(|plate| graph.node(plate.0)) {
+        let share = 100.0 * node.area / (4.0 * std::f32::consts::PI);
+        lines.push(match node.kind() {
+            Some(kind) => format!(
+                "{kind:?} plate, {} faces, {share:.1}% of the globe",
+                node.faces
+            ),
+            None => format!("{} faces, {share:.1}% of the globe", node.faces),
+        });
+        let borders: Vec<String> = graph
+            .neighbours(node.plate)
+            .map(|(other, edge)| format!("{other} ({} sides)", edge.sides.len()))
+            .collect();
+        if !borders.is_empty() {
+            lines.push(format!("Borders plates {}", borders.join(", ")));
+        }
+    }
+    match boundary {
+        Some(boundary) => {
+            lines.push(format!("Plate boundary, {} sides", boundary.sides.len()));
+            // one line for each side that's been classified, against the plate across it
+            for side in &boundary.sides {
+                let (Some(class), Ok((_, _, Some(across), ..))) =
+                    (side.class, query_faces.get(side.across))
+                else {
+                    continue;
+                };
+                lines.push(format!(
+                    "  {:?} with plate {}, magnitude {:.2}",
+                    class.kind, across.0, class.magnitude
+                ));
+            }
+        }
+        None => lines.push("Not on a boundary".to_string()),
+    }
+    if land {
+        lines.push("Land".to_string());
+    } else if sea {
+        lines.push("Sea".to_string());
+    }
+    if let Some((crust, thickness)) = selected.0.and_then(|entity_id| q_crust.get(entity_id).ok()) {
+        lines.push(format!("{crust:?} crust, {:.1} km thick", thickness.0));
+    }
+    if let Some(elevation) = elevation {
+        lines.push(format!("Elevation {:.2} km", elevation.0));
+    }
+    if let Some(velocity) = velocity {
+        let v = velocity.velocity;
+        lines.push(format!("Velocity ({:.3}, {:.3}, {:.3})", v.x, v.y, v.z));
+    }
+
+    let mut neighbour_indices: Vec<usize> = neighbours
+        .iter()
+        .filter_map(|entity_id| q_indices.get(*entity_id).ok())
+        .map(|face| face.index)
+        .collect();
+    neighbour_indices.sort_unstable();
+    let neighbour_list: Vec<String> = neighbour_indices.iter().map(ToString::to_string).collect();
+    lines.push(format!("Neighbours {}", neighbour_list.join(", ")));
+    lines.push("Esc to close".to_string());
+
+    text.set_if_neq(Text::new(lines.join("\n")));
+    node.display = Display::Flex;
+}
+
+/// Pick faces on the globe with the mouse, only makes sense with a window
+pub struct InspectPlugin;
+
+impl Plugin for InspectPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(MeshPickingPlugin)
+            .init_resource::<HoveredFace>()
+            .init_resource::<SelectedFace>()
+            .init_resource::<PressPosition>()
+            .init_resource::<FaceGraph>()
+            .add_observer(hover_face)
+            .add_observer(unhover_face)
+            .add_observer(press_face)
+            .add_observer(click_face)
+            .add_systems(Startup, setup_inspect_ui)
+            .add_systems(
+                Update,
+                (
+                    update_face_graph,
+                    handle_inspect_keys,
+                    forget_despawned_faces,
+                    draw_highlights,
+                    update_tooltip,
+                    update_inspect_panel,
+                )
+                    .chain(),
+            );
+    }
+}
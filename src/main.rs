@@ -1,33 +1,121 @@
 #![warn(clippy::pedantic)]
 #![allow(clippy::cast_possible_truncation)]
+// bevy hands systems their params by value (`Res`, `Query`, `On`, ...) and the face queries are
+// tuples of many components, so these two pedantic lints fire on nearly every system
+#![allow(clippy::needless_pass_by_value, clippy::type_complexity)]
 //! Generate a sphere of hexagons and pentagons, render it nicely
 
+mod boundary_lines;
+mod cli;
+mod config;
+mod crust;
+mod elevation;
+mod export;
+mod growth;
+mod headless;
+mod inspect;
+mod map_mode;
+mod motion;
+mod noise;
+mod partition;
+mod plate_graph;
+mod relief;
+mod removed;
+mod save;
+mod sea_level;
+mod seed;
 mod setup;
+mod simulation;
 mod states;
 mod ui;
 mod worldgen;
 
 use bevy::prelude::*;
 use bevy_panorbit_camera::{PanOrbitCamera, PanOrbitCameraPlugin};
+use clap::Parser;
 
 use std::time::Duration;
 
-use crate::{setup::SetupPlugin, states::StatePlugin, ui::UiPlugin, worldgen::WorldGenPlugin};
+use crate::{
+    boundary_lines::BoundaryLinesPlugin,
+    cli::{Cli, Command},
+    config::WorldGenConfig,
+    export::ExportPlugin,
+    growth::PlateSizesUiPlugin,
+    inspect::InspectPlugin,
+    map_mode::MapModePlugin,
+    motion::MotionOverlayPlugin,
+    plate_graph::{PlateGraphPlugin, PlateGraphViewPlugin},
+    relief::ReliefPlugin,
+    save::{PendingLoad, SaveFile, SavePlugin},
+    sea_level::{SeaLevelPlugin, SeaLevelUiPlugin},
+    seed::{SeedPlugin, WorldSeed},
+    setup::{GlobeRenderPlugin, SetupPlugin},
+    simulation::{SimulationControlsPlugin, SimulationPlugin},
+    states::StatePlugin,
+    ui::UiPlugin,
+    worldgen::{WorldGenControlsPlugin, WorldGenPlugin},
+};
 
 const TICK_RATE: u64 = 100;
 
 fn main() -> AppExit {
+    let cli = Cli::parse();
+    let save = match cli.load.as_deref().map(SaveFile::read).transpose() {
+        Ok(save) => save,
+        Err(e) => {
+            eprintln!("{e}");
+            return AppExit::error();
+        }
+    };
+
+    // a save brings its own seed and config, the globe has to match the one it was saved from
+    let (seed, config) = match &save {
+        Some(save) => (save.seed(), save.config().clone()),
+        None => match WorldGenConfig::from_cli(&cli) {
+            Ok(config) => (cli.seed.map_or_else(WorldSeed::random, WorldSeed), config),
+            Err(e) => {
+                eprintln!("{e}");
+                return AppExit::error();
+            }
+        },
+    };
+
+    if let Some(Command::Generate(args)) = cli.command {
+        return headless::run(seed, config, save, args);
+    }
+
     App::new()
         .add_plugins(DefaultPlugins)
         .add_plugins(PanOrbitCameraPlugin)
         .insert_resource(Time::<Fixed>::from_duration(Duration::from_millis(
             TICK_RATE,
         )))
+        .insert_resource(seed)
+        .insert_resource(config)
+        .insert_resource(PendingLoad(save))
         .add_systems(Startup, setup)
+        .add_plugins(SeedPlugin)
         .add_plugins(SetupPlugin)
+        .add_plugins(GlobeRenderPlugin)
+        .add_plugins(MapModePlugin)
+        .add_plugins(BoundaryLinesPlugin)
+        .add_plugins(ReliefPlugin)
+        .add_plugins(InspectPlugin)
+        .add_plugins(MotionOverlayPlugin)
+        .add_plugins(PlateGraphViewPlugin)
+        .add_plugins(ExportPlugin)
+        .add_plugins(SavePlugin)
         .add_plugins(WorldGenPlugin)
+        .add_plugins(WorldGenControlsPlugin)
+        .add_plugins(PlateGraphPlugin)
+        .add_plugins(SeaLevelPlugin)
+        .add_plugins(SimulationPlugin)
+        .add_plugins(SimulationControlsPlugin)
         .add_plugins(StatePlugin)
         .add_plugins(UiPlugin)
+        .add_plugins(PlateSizesUiPlugin)
+        .add_plugins(SeaLevelUiPlugin)
         .add_systems(Update, update_directional_light)
         .run()
 }
@@ -0,0 +1,349 @@
+// faces are coloured from whatever they hold and the map mode being shown,
+// so switching mode never loses anything an earlier stage made
+
+use bevy::ecs::query::QueryData;
+use bevy::mesh::VertexAttributeValues;
+use bevy::prelude::*;
+use clap::ValueEnum;
+
+use crate::crust::{CrustThickness, CrustType};
+use crate::elevation::{Elevation, elevation_colour};
+use crate::removed::AnyRemoved;
+use crate::sea_level::SeaLevel;
+use crate::setup::{Face, FaceVertices, PlatePalette};
+use crate::states::{GameState, WorldGenState};
+use crate::worldgen::{BoundarySide, FacePlateVelocity, GenStage, Land, Plate, PlateBoundary, Sea};
+
+const LAND_COLOUR: Color = Color::srgb(0.565, 0.933, 0.565);
+const SEA_COLOUR: Color = Color::srgb(0.0, 0.412, 0.58);
+const OCEANIC_CRUST_COLOUR: Color = Color::srgb(0.1, 0.2, 0.4);
+const THIN_CONTINENT_COLOUR: Color = Color::srgb(0.8, 0.7, 0.5);
+const THICK_CONTINENT_COLOUR: Color = Color::srgb(0.45, 0.25, 0.1);
+const BOUNDARY_COLOUR: Color = Color::BLACK;
+/// Faces that don't have what the mode shows yet
+const MISSING_COLOUR: Color = Color::WHITE;
+
+/// Which face attribute the globe (and anything exported from it) is coloured by
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum MapMode {
+    #[default]
+    Plates,
+    /// Plates with their boundaries drawn over them as lines,
+    /// coloured by their kind once they've been classified
+    Boundaries,
+    LandSea,
+    /// Ocean floor and continent, continents darker the thicker their crust
+    Crust,
+    /// Direction of motion as hue, speed as brightness
+    Velocity,
+    Elevation,
+}
+
+impl MapMode {
+    pub const ALL: [MapMode; 6] = [
+        MapMode::Plates,
+        MapMode::Boundaries,
+        MapMode::LandSea,
+        MapMode::Crust,
+        MapMode::Velocity,
+        MapMode::Elevation,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MapMode::Plates => "Plates",
+            MapMode::Boundaries => "Boundaries",
+            MapMode::LandSea => "Land / sea",
+            MapMode::Crust => "Crust",
+            MapMode::Velocity => "Velocity",
+            MapMode::Elevation => "Elevation",
+        }
+    }
+
+    fn next(self) -> MapMode {
+        let i = MapMode::ALL
+            .iter()
+            .position(|mode| *mode == self)
+            .unwrap_or(0);
+        MapMode::ALL[(i + 1) % MapMode::ALL.len()]
+    }
+
+    /// The mode that best shows what a stage made
+    fn for_stage(stage: GenStage) -> MapMode {
+        match stage {
+            GenStage::Plates => MapMode::Plates,
+            GenStage::PlateBoundaries | GenStage::BoundaryKinds => MapMode::Boundaries,
+            GenStage::Continents => MapMode::Crust,
+            GenStage::PlateVelocities => MapMode::Velocity,
+            GenStage::Elevation => MapMode::Elevation,
+        }
+    }
+
+    /// Whether plate boundaries are drawn over the faces in this mode
+    pub fn shows_boundaries(self) -> bool {
+        self == MapMode::Boundaries
+    }
+
+    pub fn colour(
+        self,
+        palette: &PlatePalette,
+        sea_level: SeaLevel,
+        face: &FaceColourQueryItem,
+    ) -> Color {
+        let plate_colour = face.plate.map(|plate| palette[plate.0]);
+        match self {
+            // the boundaries are lines between the faces, the faces keep their plate's colour
+            MapMode::Plates | MapMode::Boundaries => plate_colour.unwrap_or(MISSING_COLOUR),
+            MapMode::LandSea => match (face.land, face.sea) {
+                (true, _) => LAND_COLOUR,
+                (_, true) => SEA_COLOUR,
+                _ => MISSING_COLOUR,
+            },
+            MapMode::Crust => match (face.crust, face.thickness) {
+                (Some(CrustType::Oceanic), _) => OCEANIC_CRUST_COLOUR,
+                (Some(CrustType::Continental), Some(thickness)) => {
+                    let t = (thickness.0 - CrustThickness::MARGIN.0)
+                        / (CrustThickness::CRATON.0 - CrustThickness::MARGIN.0);
+                    THIN_CONTINENT_COLOUR.mix(&THICK_CONTINENT_COLOUR, t.clamp(0.0, 1.0))
+                }
+                _ => MISSING_COLOUR,
+            },
+            MapMode::Velocity => face.velocity.map_or(MISSING_COLOUR, |velocity| {
+                velocity_colour(face.face.centre_pos, velocity.velocity)
+            }),
+            // coloured by height above the sea, so the coasts follow it as it moves
+            MapMode::Elevation => face.elevation.map_or(MISSING_COLOUR, |elevation| {
+                elevation_colour(elevation.0 - sea_level.level)
+            }),
+        }
+    }
+}
+
+/// Everything on a face that any map mode might colour it by
+#[derive(QueryData)]
+pub struct FaceColourQuery {
+    pub face: &'static Face,
+    pub plate: Option<&'static Plate>,
+    pub boundary: Option<&'static PlateBoundary>,
+    pub land: Has<Land>,
+    pub sea: Has<Sea>,
+    pub crust: Option<&'static CrustType>,
+    pub thickness: Option<&'static CrustThickness>,
+    pub velocity: Option<&'static FacePlateVelocity>,
+    pub elevation: Option<&'static Elevation>,
+}
+
+/// Colour of the boundary line along a side, from how the side is classified
+pub fn boundary_colour(side: &BoundarySide) -> Color {
+    side.class
+        .map_or(BOUNDARY_COLOUR, |class| class.kind.colour())
+}
+
+/// Hue from the compass direction the face is moving in, brighter the faster it goes
+fn velocity_colour(position: Vec3, velocity: Vec3) -> Color {
+    // +z is north, so east is around the z axis
+    let east = Vec3::Z.cross(position).normalize_or(Vec3::X);
+    let north = position.cross(east).normalize_or(Vec3::Y);
+    let bearing = velocity.dot(north).atan2(velocity.dot(east)).to_degrees();
+    let speed = velocity.length().min(1.0);
+    Color::hsl(bearing.rem_euclid(360.0), 0.85, 0.15 + 0.55 * speed)
+}
+
+/// Removing any of these means some faces need colouring again
+type RemovedFaceData<'w, 's> = (
+    RemovedComponents<'w, 's, Plate>,
+    RemovedComponents<'w, 's, Land>,
+    RemovedComponents<'w, 's, Sea>,
+    RemovedComponents<'w, 's, CrustType>,
+    RemovedComponents<'w, 's, FacePlateVelocity>,
+    RemovedComponents<'w, 's, Elevation>,
+);
+
+type FaceColourChanged = Or<(
+    Added<FaceVertices>,
+    Changed<Plate>,
+    Added<Land>,
+    Added<Sea>,
+    Changed<CrustType>,
+    Changed<CrustThickness>,
+    Changed<FacePlateVelocity>,
+    Changed<Elevation>,
+)>;
+
+/// The one place face colours are written, every face when the mode changes,
+/// otherwise just the faces whose data changed
+fn colour_faces(
+    mode: Res<MapMode>,
+    palette: Res<PlatePalette>,
+    sea_level: Res<SeaLevel>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut removed: RemovedFaceData,
+    query_faces: Query<(&FaceVertices, FaceColourQuery)>,
+    changed_faces: Query<(&FaceVertices, FaceColourQuery), FaceColourChanged>,
+) {
+    let recolour_all = removed.any_removed()
+        || mode.is_changed()
+        || palette.is_changed()
+        || (sea_level.is_changed() && *mode == MapMode::Elevation);
+    let faces = if recolour_all {
+        query_faces.iter().collect::<Vec<_>>()
+    } else {
+        changed_faces.iter().collect()
+    };
+
+    for (face_vertices, face) in faces {
+        let colour = LinearRgba::from(mode.colour(&palette, *sea_level, &face)).to_f32_array();
+        if let Some(mesh) = meshes.get_mut(&face_vertices.mesh)
+            && let Some(VertexAttributeValues::Float32x4(colours)) =
+                mesh.attribute_mut(Mesh::ATTRIBUTE_COLOR)
+        {
+            colours[face_vertices.range.clone()].fill(colour);
+        }
+    }
+}
+
+/// Show what each stage makes as it's made, the mode can still be flipped at any time
+fn follow_stage(state: Res<State<WorldGenState>>, mut mode: ResMut<MapMode>) {
+    if let Some(stage) = GenStage::active_in(state.get()) {
+        mode.set_if_neq(MapMode::for_stage(stage));
+    }
+}
+
+fn handle_map_mode_keys(keyboard_input: Res<ButtonInput<KeyCode>>, mut mode: ResMut<MapMode>) {
+    if keyboard_input.just_pressed(KeyCode::KeyM) {
+        *mode = mode.next();
+    }
+}
+
+#[derive(Component)]
+struct MapModeDropdown;
+
+#[derive(Component)]
+struct MapModeDropdownText;
+
+#[derive(Component)]
+struct MapModeOptions;
+
+#[derive(Component)]
+struct MapModeOption(MapMode);
+
+const BUTTON_COLOUR: Color = Color::srgba(0.1, 0.1, 0.1, 0.8);
+const CURRENT_OPTION_COLOUR: Color = Color::srgba(0.3, 0.3, 0.45, 0.9);
+
+fn setup_map_mode_ui(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(5.0),
+                width: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            // only the buttons should catch the pointer, not the whole strip across the top
+            Pickable::IGNORE,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Button,
+                    Node {
+                        padding: UiRect::axes(Val::Px(8.0), Val::Px(4.0)),
+                        ..default()
+                    },
+                    BackgroundColor(BUTTON_COLOUR),
+                    MapModeDropdown,
+                ))
+                .with_child((Text::new(""), MapModeDropdownText));
+            parent
+                .spawn((
+                    Node {
+                        flex_direction: FlexDirection::Column,
+                        display: Display::None,
+                        ..default()
+                    },
+                    MapModeOptions,
+                ))
+                .with_children(|options| {
+                    for mode in MapMode::ALL {
+                        options
+                            .spawn((
+                                Button,
+                                Node {
+                                    padding: UiRect::axes(Val::Px(8.0), Val::Px(2.0)),
+                                    ..default()
+                                },
+                                BackgroundColor(BUTTON_COLOUR),
+                                MapModeOption(mode),
+                            ))
+                            .with_child(Text::new(mode.name()));
+                    }
+                });
+        });
+}
+
+/// Clicking the dropdown opens the list of modes, clicking one of those picks it and closes the list
+fn handle_map_mode_buttons(
+    q_buttons: Query<
+        (&Interaction, Has<MapModeDropdown>, Option<&MapModeOption>),
+        Changed<Interaction>,
+    >,
+    mut options: Single<&mut Node, With<MapModeOptions>>,
+    mut mode: ResMut<MapMode>,
+) {
+    for (interaction, dropdown, option) in q_buttons.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        if dropdown {
+            options.display = match options.display {
+                Display::None => Display::Flex,
+                _ => Display::None,
+            };
+        }
+        if let Some(option) = option {
+            *mode = option.0;
+            options.display = Display::None;
+        }
+    }
+}
+
+fn update_map_mode_ui(
+    mode: Res<MapMode>,
+    mut q_text: Query<&mut Text, With<MapModeDropdownText>>,
+    mut q_options: Query<(&MapModeOption, &mut BackgroundColor)>,
+) {
+    for mut text in &mut q_text {
+        text.0 = format!("Map mode (M): {}", mode.name());
+    }
+    for (option, mut background) in &mut q_options {
+        background.0 = if option.0 == *mode {
+            CURRENT_OPTION_COLOUR
+        } else {
+            BUTTON_COLOUR
+        };
+    }
+}
+
+/// Colours the globe by the current map mode, only needed when there is a globe to look at
+pub struct MapModePlugin;
+
+impl Plugin for MapModePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MapMode>()
+            .add_systems(Startup, setup_map_mode_ui)
+            .add_systems(
+                Update,
+                (
+                    follow_stage
+                        .run_if(in_state(GameState::WorldGen).and(state_changed::<WorldGenState>)),
+                    handle_map_mode_keys,
+                    handle_map_mode_buttons,
+                    update_map_mode_ui.run_if(resource_changed::<MapMode>),
+                    colour_faces,
+                )
+                    .chain(),
+            );
+    }
+}
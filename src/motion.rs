@@ -0,0 +1,191 @@
+// draw how the plates move, arrows on the faces and a marker on each plate's euler pole,
+// so the kinematics can be checked by eye
+
+use bevy::prelude::*;
+
+use crate::elevation::Elevation;
+use crate::relief::ReliefSettings;
+use crate::removed::AnyRemoved;
+use crate::setup::{Face, PlatePalette};
+use crate::worldgen::{FacePlateVelocity, Plate, fit_plate_rotations};
+
+/// Roughly how many arrows to spread over the globe, however many faces it has
+const MAX_ARROWS: usize = 1500;
+/// Arrows float a little above the faces so they aren't hidden in them
+const LIFT: f32 = 1.004;
+const ARROW_COLOUR: Color = Color::WHITE;
+const POLE_MARKER_RADIUS: f32 = 0.03;
+
+#[derive(Resource, Default)]
+struct MotionOverlay {
+    enabled: bool,
+}
+
+/// Where each plate's euler pole is, worked back out of its face velocities
+#[derive(Resource, Default)]
+struct PlatePoles(Vec<PlatePole>);
+
+struct PlatePole {
+    plate: usize,
+    /// Rotation axis scaled by angular speed, the pole is where it comes out of the globe
+    rotation: Vec3,
+    /// The face the pole sits in, so the marker can follow the relief
+    face: Entity,
+}
+
+#[derive(Component)]
+struct MotionUiText;
+
+/// Fit the poles again whenever the velocities change
+fn update_plate_poles(
+    mut poles: ResMut<PlatePoles>,
+    query_faces: Query<(Entity, &Face, Option<(&Plate, &FacePlateVelocity)>)>,
+    changed_velocities: Query<(), Changed<FacePlateVelocity>>,
+    mut removed_velocities: RemovedComponents<FacePlateVelocity>,
+) {
+    if changed_velocities.is_empty() && !removed_velocities.any_removed() {
+        return;
+    }
+
+    let rotations = fit_plate_rotations(query_faces.iter().filter_map(|(_, face, motion)| {
+        motion.map(|(plate, velocity)| (face.centre_pos, plate.0, velocity.velocity))
+    }));
+
+    poles.0 = rotations
+        .into_iter()
+        .filter(|(_, rotation)| *rotation != Vec3::ZERO)
+        .filter_map(|(plate, rotation)| {
+            let axis = rotation.normalize();
+            query_faces
+                .iter()
+                .max_by(|(_, a, _), (_, b, _)| {
+                    a.centre_pos.dot(axis).total_cmp(&b.centre_pos.dot(axis))
+                })
+                .map(|(face, ..)| PlatePole {
+                    plate,
+                    rotation,
+                    face,
+                })
+        })
+        .collect();
+    poles.0.sort_unstable_by_key(|pole| pole.plate);
+}
+
+fn draw_velocity_arrows(
+    mut gizmos: Gizmos,
+    relief: Res<ReliefSettings>,
+    query_faces: Query<(&Face, &FacePlateVelocity, Option<&Elevation>)>,
+) {
+    let n_faces = query_faces.iter().len();
+    if n_faces == 0 {
+        return;
+    }
+    let stride = n_faces.div_ceil(MAX_ARROWS);
+    // the fastest a face can go is 1, make that about the distance between neighbouring arrows
+    #[allow(clippy::cast_precision_loss)]
+    let spacing = (4.0 * std::f32::consts::PI / (n_faces / stride) as f32).sqrt();
+
+    for (face, velocity, elevation) in query_faces.iter() {
+        if face.index % stride != 0 {
+            continue;
+        }
+        let radius = relief.radius(elevation.map_or(0.0, |elevation| elevation.0)) * LIFT;
+        let start = face.centre_pos.normalize() * radius;
+        let offset = velocity.velocity * spacing;
+        if offset.length_squared() < 1e-10 {
+            continue;
+        }
+        gizmos
+            .arrow(start, start + offset, ARROW_COLOUR)
+            .with_tip_length(offset.length() * 0.3);
+    }
+}
+
+/// A ring in the plate's colour around each pole, with a spoke out of it as long as the plate is fast
+fn draw_pole_markers(
+    mut gizmos: Gizmos,
+    poles: Res<PlatePoles>,
+    palette: Res<PlatePalette>,
+    relief: Res<ReliefSettings>,
+    q_elevations: Query<Option<&Elevation>>,
+) {
+    for pole in &poles.0 {
+        let elevation = q_elevations.get(pole.face).ok().flatten();
+        let radius = relief.radius(elevation.map_or(0.0, |elevation| elevation.0)) * LIFT;
+        let axis = pole.rotation.normalize();
+        let centre = axis * radius;
+        let colour = palette[pole.plate];
+
+        let isometry = Isometry3d::new(centre, Quat::from_rotation_arc(Vec3::Z, axis));
+        gizmos.circle(isometry, POLE_MARKER_RADIUS, colour);
+        gizmos.circle(isometry, POLE_MARKER_RADIUS * 0.5, colour);
+        gizmos.line(
+            centre,
+            centre + axis * (0.05 + 0.2 * pole.rotation.length()),
+            colour,
+        );
+    }
+}
+
+fn handle_motion_keys(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut overlay: ResMut<MotionOverlay>,
+) {
+    if keyboard_input.just_pressed(KeyCode::KeyK) {
+        overlay.enabled = !overlay.enabled;
+    }
+}
+
+fn setup_motion_ui(mut commands: Commands) {
+    commands.spawn((
+        Text::new(""),
+        // above the relief controls, the bottom right corner belongs to the stage text
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(50.0),
+            left: Val::Px(5.0),
+            ..default()
+        },
+        MotionUiText,
+    ));
+}
+
+fn update_motion_ui(
+    overlay: Res<MotionOverlay>,
+    poles: Res<PlatePoles>,
+    mut q_text: Query<&mut Text, With<MotionUiText>>,
+) {
+    for mut text in &mut q_text {
+        text.0 = if !overlay.enabled {
+            "Plate motion (K): off".to_string()
+        } else if poles.0.is_empty() {
+            "Plate motion (K): on, no velocities yet".to_string()
+        } else {
+            format!("Plate motion (K): on, {} poles", poles.0.len())
+        };
+    }
+}
+
+/// Overlay of plate velocities and euler poles, toggled with K
+pub struct MotionOverlayPlugin;
+
+impl Plugin for MotionOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MotionOverlay>()
+            .init_resource::<PlatePoles>()
+            .add_systems(Startup, setup_motion_ui)
+            .add_systems(
+                Update,
+                (
+                    handle_motion_keys,
+                    update_plate_poles,
+                    (draw_velocity_arrows, draw_pole_markers)
+                        .run_if(|overlay: Res<MotionOverlay>| overlay.enabled),
+                    update_motion_ui.run_if(
+                        resource_changed::<MotionOverlay>.or(resource_changed::<PlatePoles>),
+                    ),
+                )
+                    .chain(),
+            );
+    }
+}
@@ -0,0 +1,66 @@
+// smooth random noise over space, for anything that wants variation without an obvious pattern
+
+use bevy::prelude::*;
+
+/// Seeded 3D value noise, random values on an integer lattice smoothly blended between
+#[derive(Clone, Copy, Debug)]
+pub struct Noise {
+    seed: u64,
+}
+
+impl Noise {
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    /// Noise at a point, between -1 and 1, changing over a distance of about 1
+    pub fn sample(self, point: Vec3) -> f32 {
+        let cell = point.floor();
+        let t = point - cell;
+        // smoothstep so the blend has no creases along the lattice
+        let t = t * t * (Vec3::splat(3.0) - 2.0 * t);
+        let [x, y, z] = cell.as_ivec3().to_array();
+
+        let corner = |dx, dy, dz| self.lattice(x + dx, y + dy, z + dz);
+        let x00 = corner(0, 0, 0).lerp(corner(1, 0, 0), t.x);
+        let x10 = corner(0, 1, 0).lerp(corner(1, 1, 0), t.x);
+        let x01 = corner(0, 0, 1).lerp(corner(1, 0, 1), t.x);
+        let x11 = corner(0, 1, 1).lerp(corner(1, 1, 1), t.x);
+        x00.lerp(x10, t.y).lerp(x01.lerp(x11, t.y), t.z)
+    }
+
+    /// Several octaves of noise added together, each at twice the frequency and half the weight
+    /// of the last, still between -1 and 1
+    pub fn fbm(self, point: Vec3, octaves: u32) -> f32 {
+        let mut total = 0.0;
+        let mut weight = 1.0;
+        let mut weights = 0.0;
+        let mut point = point;
+        for octave in 0..octaves {
+            // each octave is its own field, otherwise they all line up at the origin
+            total += weight * Noise::new(self.seed.wrapping_add(u64::from(octave))).sample(point);
+            weights += weight;
+            weight *= 0.5;
+            point *= 2.0;
+        }
+        if weights > 0.0 { total / weights } else { 0.0 }
+    }
+
+    /// The random value at a lattice point, between -1 and 1
+    fn lattice(self, x: i32, y: i32, z: i32) -> f32 {
+        // splitmix64 over the seed and coordinates
+        let mut h = self.seed;
+        for coordinate in [x, y, z] {
+            h = h
+                .wrapping_add(0x9E37_79B9_7F4A_7C15)
+                .wrapping_add(u64::from(coordinate.cast_unsigned()));
+            h = (h ^ (h >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            h = (h ^ (h >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            h ^= h >> 31;
+        }
+        // the top 24 bits fit an f32 exactly
+        #[allow(clippy::cast_precision_loss)]
+        let unit = (h >> 40) as f32 / (1u64 << 24) as f32;
+        unit * 2.0 - 1.0
+    }
+}
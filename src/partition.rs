@@ -0,0 +1,258 @@
+// split the globe between the plate seeds in one go rather than growing plates out from them,
+// and tidy up whatever partition the plates end up with
+
+use bevy::prelude::*;
+
+use crate::noise::Noise;
+
+/// Octaves summed for the domain warp
+const WARP_OCTAVES: u32 = 3;
+/// Gap between the seeds of the warp's three noise fields, more than the octaves use
+const WARP_FIELD_STRIDE: u64 = 64;
+
+/// Noise that pushes face centres about before they're measured, so boundaries wander
+#[derive(Clone, Copy)]
+pub struct Warp {
+    pub seed: u64,
+    /// Furthest a centre can be pushed, as a fraction of the globe's radius
+    pub strength: f32,
+    /// How many blobs of noise fit across the globe
+    pub scale: f32,
+}
+
+impl Warp {
+    fn apply(self, point: Vec3) -> Vec3 {
+        let field = |i| {
+            Noise::new(self.seed.wrapping_add(i * WARP_FIELD_STRIDE))
+                .fbm(point * self.scale, WARP_OCTAVES)
+        };
+        (point + self.strength * Vec3::new(field(0), field(1), field(2))).normalize_or(point)
+    }
+}
+
+/// Give each face centre the index of the seed nearest to it by great circle distance.
+/// Each round of relaxation moves the seeds to the middle of their plates and assigns again,
+/// which evens out the plate sizes
+pub fn voronoi_plates(
+    centres: &[Vec3],
+    seeds: &[Vec3],
+    relaxation: u32,
+    warp: Option<Warp>,
+) -> Vec<usize> {
+    let points: Vec<Vec3> = centres
+        .iter()
+        .map(|centre| {
+            let centre = centre.normalize();
+            warp.map_or(centre, |warp| warp.apply(centre))
+        })
+        .collect();
+    let mut seeds: Vec<Vec3> = seeds.iter().map(|seed| seed.normalize()).collect();
+
+    let mut plates = nearest_seeds(&points, &seeds);
+    for _ in 0..relaxation {
+        let mut sums = vec![Vec3::ZERO; seeds.len()];
+        for (point, plate) in points.iter().zip(&plates) {
+            sums[*plate] += *point;
+        }
+        // a plate that lost every face keeps its seed where it was
+        for (seed, sum) in seeds.iter_mut().zip(sums) {
+            *seed = sum.try_normalize().unwrap_or(*seed);
+        }
+        plates = nearest_seeds(&points, &seeds);
+    }
+    plates
+}
+
+/// Closest by great circle distance is the one with the largest dot product
+fn nearest_seeds(points: &[Vec3], seeds: &[Vec3]) -> Vec<usize> {
+    points
+        .iter()
+        .map(|point| {
+            seeds
+                .iter()
+                .enumerate()
+                .max_by(|(_, a), (_, b)| a.dot(*point).total_cmp(&b.dot(*point)))
+                .map_or(0, |(i, _)| i)
+        })
+        .collect()
+}
+
+/// A connected patch of faces all on the same plate
+struct Region {
+    plate: usize,
+    faces: Vec<usize>,
+}
+
+/// Fold plates smaller than `min_size`, and any pieces of a plate cut off from the rest of it,
+/// into the neighbouring plate they share the most edges with, smallest first.
+/// `plates` holds each face's plate and `neighbours` each face's neighbouring faces.
+/// Returns the old plate ids in their new order, the surviving plates first so each one's new id
+/// is its place in the list, then the plates that were merged away
+pub fn merge_small_plates(
+    plates: &mut [usize],
+    neighbours: &[Vec<usize>],
+    min_size: usize,
+    n_plates: usize,
+) -> Vec<usize> {
+    loop {
+        let regions = connected_regions(plates, neighbours);
+        let mut sizes = vec![0; n_plates];
+        let mut main_regions = vec![None; n_plates];
+        for (i, region) in regions.iter().enumerate() {
+            sizes[region.plate] += region.faces.len();
+            let main = main_regions[region.plate].get_or_insert(i);
+            if region.faces.len() > regions[*main].faces.len() {
+                *main = i;
+            }
+        }
+
+        let Some(region) = regions
+            .iter()
+            .enumerate()
+            .filter(|(i, region)| {
+                main_regions[region.plate] != Some(*i) || sizes[region.plate] < min_size
+            })
+            .map(|(_, region)| region)
+            .min_by_key(|region| region.faces.len())
+        else {
+            break;
+        };
+
+        let mut border = vec![0; n_plates];
+        for &face in &region.faces {
+            for &neighbour in &neighbours[face] {
+                if plates[neighbour] != region.plate {
+                    border[plates[neighbour]] += 1;
+                }
+            }
+        }
+        // ties go to the lower numbered plate, a region with no border is the whole globe
+        let Some((into, _)) = border
+            .iter()
+            .enumerate()
+            .filter(|(_, edges)| **edges > 0)
+            .max_by_key(|(plate, edges)| (**edges, std::cmp::Reverse(*plate)))
+        else {
+            break;
+        };
+        for &face in &region.faces {
+            plates[face] = into;
+        }
+    }
+
+    let mut alive = vec![false; n_plates];
+    for plate in plates.iter() {
+        alive[*plate] = true;
+    }
+    let (mut order, merged): (Vec<usize>, Vec<usize>) =
+        (0..n_plates).partition(|plate| alive[*plate]);
+    let mut new_ids = vec![0; n_plates];
+    for (new_id, old_id) in order.iter().enumerate() {
+        new_ids[*old_id] = new_id;
+    }
+    for plate in plates.iter_mut() {
+        *plate = new_ids[*plate];
+    }
+    order.extend(merged);
+    order
+}
+
+/// Split every plate into its connected pieces, in the order their first face appears
+fn connected_regions(plates: &[usize], neighbours: &[Vec<usize>]) -> Vec<Region> {
+    let mut seen = vec![false; plates.len()];
+    let mut regions = Vec::new();
+    for start in 0..plates.len() {
+        if seen[start] {
+            continue;
+        }
+        seen[start] = true;
+        let plate = plates[start];
+        let mut faces = vec![start];
+        let mut next = 0;
+        while next < faces.len() {
+            for &neighbour in &neighbours[faces[next]] {
+                if !seen[neighbour] && plates[neighbour] == plate {
+                    seen[neighbour] = true;
+                    faces.push(neighbour);
+                }
+            }
+            next += 1;
+        }
+        regions.push(Region { plate, faces });
+    }
+    regions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Faces in a ring, each touching the one either side of it
+    fn ring(n: usize) -> Vec<Vec<usize>> {
+        (0..n).map(|i| vec![(i + n - 1) % n, (i + 1) % n]).collect()
+    }
+
+    #[test]
+    fn nothing_to_merge_keeps_the_plates() {
+        let mut plates = vec![0, 0, 0, 1, 1, 1, 2, 2, 2];
+        let order = merge_small_plates(&mut plates, &ring(9), 0, 3);
+        assert_eq!(plates, [0, 0, 0, 1, 1, 1, 2, 2, 2]);
+        assert_eq!(order, [0, 1, 2]);
+    }
+
+    #[test]
+    fn small_plate_merges_into_its_longest_border() {
+        // plate 1 shares two sides with plate 2 and one with plate 0
+        let mut plates = vec![0, 0, 0, 1, 1, 2, 2, 2, 2, 2];
+        let neighbours = vec![
+            vec![9, 1],
+            vec![0, 2],
+            vec![1, 3],
+            vec![2, 4, 5],
+            vec![3, 5, 6],
+            vec![4, 6, 3],
+            vec![5, 7, 4],
+            vec![6, 8],
+            vec![7, 9],
+            vec![8, 0],
+        ];
+        let order = merge_small_plates(&mut plates, &neighbours, 3, 3);
+        assert_eq!(plates, [0, 0, 0, 1, 1, 1, 1, 1, 1, 1]);
+        // the merged plate's colour goes to the back
+        assert_eq!(order, [0, 2, 1]);
+    }
+
+    #[test]
+    fn ties_go_to_the_lower_numbered_plate() {
+        let mut plates = vec![0, 0, 0, 0, 1, 2, 2, 2, 2, 2];
+        let order = merge_small_plates(&mut plates, &ring(10), 2, 3);
+        assert_eq!(plates, [0, 0, 0, 0, 0, 1, 1, 1, 1, 1]);
+        assert_eq!(order, [0, 2, 1]);
+    }
+
+    #[test]
+    fn stray_pieces_join_a_neighbour_even_without_a_minimum() {
+        // face 6 is cut off from the rest of plate 0
+        let mut plates = vec![0, 0, 0, 1, 1, 1, 0, 2, 2, 2];
+        let order = merge_small_plates(&mut plates, &ring(10), 0, 3);
+        assert_eq!(plates, [0, 0, 0, 1, 1, 1, 1, 2, 2, 2]);
+        assert_eq!(order, [0, 1, 2]);
+    }
+
+    #[test]
+    fn empty_plates_are_numbered_out() {
+        // plate 1 never got any faces
+        let mut plates = vec![0, 0, 0, 2, 2, 2];
+        let order = merge_small_plates(&mut plates, &ring(6), 0, 3);
+        assert_eq!(plates, [0, 0, 0, 1, 1, 1]);
+        assert_eq!(order, [0, 2, 1]);
+    }
+
+    #[test]
+    fn a_single_plate_covering_the_globe_stays_put() {
+        let mut plates = vec![0; 6];
+        let order = merge_small_plates(&mut plates, &ring(6), 100, 1);
+        assert_eq!(plates, [0; 6]);
+        assert_eq!(order, [0]);
+    }
+}
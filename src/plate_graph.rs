@@ -0,0 +1,271 @@
+// which plates touch which and along how much boundary, kept up to date for later stages
+// and the UI to ask about, with a view that draws it over the globe
+
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+
+use crate::crust::CrustType;
+use crate::removed::AnyRemoved;
+use crate::setup::{Face, PlatePalette};
+use crate::worldgen::{Plate, PlateBoundary};
+
+/// The graph floats above the globe so the relief doesn't swallow it
+const GRAPH_LIFT: f32 = 1.03;
+const EDGE_COLOUR: Color = Color::WHITE;
+/// A node as big as the whole globe would get this radius, smaller plates scale down by area
+const NODE_SCALE: f32 = 0.25;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlateKind {
+    Continental,
+    Oceanic,
+}
+
+pub struct PlateNode {
+    pub plate: usize,
+    pub faces: usize,
+    /// Area on the unit sphere, the whole globe is 4π
+    pub area: f32,
+    /// Middle of the plate on the unit sphere, weighted by face area
+    pub centroid: Vec3,
+    /// Share of the plate's faces that are continental crust, once continents have been made
+    pub continental_fraction: Option<f32>,
+}
+
+impl PlateNode {
+    /// Whether the plate is mostly continent or mostly ocean, once continents have been made
+    pub fn kind(&self) -> Option<PlateKind> {
+        self.continental_fraction.map(|continental| {
+            if continental >= 0.5 {
+                PlateKind::Continental
+            } else {
+                PlateKind::Oceanic
+            }
+        })
+    }
+}
+
+/// The boundary between two plates
+pub struct PlateEdge {
+    /// The two plates, the lower numbered one first
+    pub plates: (usize, usize),
+    /// Total length of the sides the plates share, on the unit sphere
+    pub length: f32,
+    /// The pair of faces either side of each shared side, the face on `plates.0` first
+    pub sides: Vec<(Entity, Entity)>,
+}
+
+/// Plates as nodes, joined wherever they share a boundary
+#[derive(Resource, Default)]
+pub struct PlateGraph {
+    /// One per plate, in plate order
+    pub nodes: Vec<PlateNode>,
+    /// One per pair of plates that touch, in plate order
+    pub edges: Vec<PlateEdge>,
+}
+
+impl PlateGraph {
+    pub fn node(&self, plate: usize) -> Option<&PlateNode> {
+        self.nodes
+            .binary_search_by_key(&plate, |node| node.plate)
+            .ok()
+            .map(|i| &self.nodes[i])
+    }
+
+    /// Every plate `plate` shares a boundary with, and the edge between them
+    pub fn neighbours(&self, plate: usize) -> impl Iterator<Item = (usize, &PlateEdge)> {
+        self.edges.iter().filter_map(move |edge| match edge.plates {
+            (a, b) if a == plate => Some((b, edge)),
+            (a, b) if b == plate => Some((a, edge)),
+            _ => None,
+        })
+    }
+}
+
+/// Removing any of these changes the graph
+type RemovedPlateData<'w, 's> = (
+    RemovedComponents<'w, 's, Plate>,
+    RemovedComponents<'w, 's, PlateBoundary>,
+    RemovedComponents<'w, 's, CrustType>,
+);
+
+type PlateGraphChanged = Or<(Changed<Plate>, Changed<PlateBoundary>, Changed<CrustType>)>;
+
+/// Build the graph again whenever plates, boundaries or continents change
+fn update_plate_graph(
+    mut graph: ResMut<PlateGraph>,
+    query_faces: Query<(&Face, &Plate, Option<&CrustType>)>,
+    q_boundaries: Query<(Entity, &Plate, &PlateBoundary)>,
+    changed_faces: Query<(), PlateGraphChanged>,
+    mut removed: RemovedPlateData,
+) {
+    if !removed.any_removed() && changed_faces.is_empty() {
+        return;
+    }
+
+    // faces, area, area weighted centre, continental faces, oceanic faces
+    let mut sums: HashMap<usize, (usize, f32, Vec3, usize, usize)> = HashMap::new();
+    for (face, plate, crust) in query_faces.iter() {
+        let area = face.area();
+        let sum = sums.entry(plate.0).or_insert((0, 0.0, Vec3::ZERO, 0, 0));
+        sum.0 += 1;
+        sum.1 += area;
+        sum.2 += face.centre_pos.normalize() * area;
+        sum.3 += usize::from(crust == Some(&CrustType::Continental));
+        sum.4 += usize::from(crust == Some(&CrustType::Oceanic));
+    }
+    let mut nodes: Vec<PlateNode> = sums
+        .into_iter()
+        .map(
+            |(plate, (faces, area, centre, continental, oceanic))| PlateNode {
+                plate,
+                faces,
+                area,
+                centroid: centre.normalize_or_zero(),
+                #[allow(clippy::cast_precision_loss)]
+                continental_fraction: (continental + oceanic == faces)
+                    .then(|| continental as f32 / faces as f32),
+            },
+        )
+        .collect();
+    nodes.sort_unstable_by_key(|node| node.plate);
+
+    let mut edges: HashMap<(usize, usize), PlateEdge> = HashMap::new();
+    for (entity_id, plate, boundary) in q_boundaries.iter() {
+        for side in boundary.own_sides(entity_id) {
+            let Ok((_, neighbour_plate, ..)) = query_faces.get(side.across) else {
+                continue;
+            };
+            let plates = if plate.0 < neighbour_plate.0 {
+                (plate.0, neighbour_plate.0)
+            } else {
+                (neighbour_plate.0, plate.0)
+            };
+            let edge = edges.entry(plates).or_insert_with(|| PlateEdge {
+                plates,
+                length: 0.0,
+                sides: Vec::new(),
+            });
+            edge.length += side.ends.0.distance(side.ends.1);
+            edge.sides.push(if plate.0 < neighbour_plate.0 {
+                (entity_id, side.across)
+            } else {
+                (side.across, entity_id)
+            });
+        }
+    }
+    let mut edges: Vec<PlateEdge> = edges.into_values().collect();
+    edges.sort_unstable_by_key(|edge| edge.plates);
+
+    *graph = PlateGraph { nodes, edges };
+}
+
+/// Keeps the plate graph up to date, with or without a window
+pub struct PlateGraphPlugin;
+
+impl Plugin for PlateGraphPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PlateGraph>()
+            .add_systems(Update, update_plate_graph);
+    }
+}
+
+#[derive(Resource, Default)]
+struct PlateGraphView {
+    enabled: bool,
+}
+
+#[derive(Component)]
+struct PlateGraphUiText;
+
+/// A ring in the plate's colour at each centroid, sized by area,
+/// and an arc between the centroids of every pair of plates that touch
+fn draw_plate_graph(mut gizmos: Gizmos, graph: Res<PlateGraph>, palette: Res<PlatePalette>) {
+    let longest = graph
+        .edges
+        .iter()
+        .map(|edge| edge.length)
+        .fold(0.0, f32::max);
+    for edge in &graph.edges {
+        let (Some(a), Some(b)) = (graph.node(edge.plates.0), graph.node(edge.plates.1)) else {
+            continue;
+        };
+        // longer borders are drawn brighter
+        let strength = 0.3 + 0.7 * edge.length / longest.max(f32::EPSILON);
+        gizmos.short_arc_3d_between(
+            Vec3::ZERO,
+            a.centroid * GRAPH_LIFT,
+            b.centroid * GRAPH_LIFT,
+            EDGE_COLOUR.with_alpha(strength),
+        );
+    }
+
+    for node in &graph.nodes {
+        let centre = node.centroid * GRAPH_LIFT;
+        let radius = NODE_SCALE * (node.area / (4.0 * std::f32::consts::PI)).sqrt();
+        let isometry = Isometry3d::new(centre, Quat::from_rotation_arc(Vec3::Z, node.centroid));
+        gizmos.circle(isometry, radius.max(0.005), palette[node.plate]);
+    }
+}
+
+fn handle_plate_graph_keys(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut view: ResMut<PlateGraphView>,
+) {
+    if keyboard_input.just_pressed(KeyCode::KeyP) {
+        view.enabled = !view.enabled;
+    }
+}
+
+fn setup_plate_graph_ui(mut commands: Commands) {
+    commands.spawn((
+        Text::new(""),
+        // above the plate motion label
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(75.0),
+            left: Val::Px(5.0),
+            ..default()
+        },
+        PlateGraphUiText,
+    ));
+}
+
+fn update_plate_graph_ui(
+    view: Res<PlateGraphView>,
+    graph: Res<PlateGraph>,
+    mut q_text: Query<&mut Text, With<PlateGraphUiText>>,
+) {
+    for mut text in &mut q_text {
+        text.0 = if view.enabled {
+            format!(
+                "Plate graph (P): on, {} plates, {} borders",
+                graph.nodes.len(),
+                graph.edges.len()
+            )
+        } else {
+            "Plate graph (P): off".to_string()
+        };
+    }
+}
+
+/// Draws the plate graph over the globe, toggled with P
+pub struct PlateGraphViewPlugin;
+
+impl Plugin for PlateGraphViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PlateGraphView>()
+            .add_systems(Startup, setup_plate_graph_ui)
+            .add_systems(
+                Update,
+                (
+                    handle_plate_graph_keys,
+                    draw_plate_graph.run_if(|view: Res<PlateGraphView>| view.enabled),
+                    update_plate_graph_ui.run_if(
+                        resource_changed::<PlateGraphView>.or(resource_changed::<PlateGraph>),
+                    ),
+                )
+                    .chain(),
+            );
+    }
+}
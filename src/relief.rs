@@ -0,0 +1,266 @@
+// push faces in and out by their elevation so the globe shows real relief
+
+use bevy::mesh::VertexAttributeValues;
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+
+use crate::elevation::Elevation;
+use crate::removed::AnyRemoved;
+use crate::setup::{
+    Face, FaceNeighbours, FaceVertices, build_face_positions, build_outline, side_floors,
+};
+use crate::ui::hold_camera_while_pressed;
+
+/// Radius of the planet in km, used to turn elevations into a fraction of the globe's radius
+const PLANET_RADIUS_KM: f32 = 6371.0;
+
+const MIN_EXAGGERATION: f32 = 1.0;
+const MAX_EXAGGERATION: f32 = 200.0;
+
+#[derive(Resource, Clone, Copy)]
+pub struct ReliefSettings {
+    pub enabled: bool,
+    /// How many times taller than life the terrain is drawn
+    pub exaggeration: f32,
+}
+
+impl Default for ReliefSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            exaggeration: 50.0,
+        }
+    }
+}
+
+impl ReliefSettings {
+    /// Distance from the centre of the globe for a face at this elevation
+    pub fn radius(self, elevation: f32) -> f32 {
+        if self.enabled {
+            1.0 + elevation * self.exaggeration / PLANET_RADIUS_KM
+        } else {
+            1.0
+        }
+    }
+}
+
+#[derive(Component)]
+struct ReliefUiText;
+
+#[derive(Component)]
+struct ExaggerationSlider;
+
+#[derive(Component)]
+struct ExaggerationSliderFill;
+
+/// Rewrite the globe's vertex positions whenever the relief settings or elevations change, just in
+/// the chunks with faces whose walls could have changed unless every face moved
+fn apply_relief(
+    settings: Res<ReliefSettings>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut query_faces: Query<(
+        Entity,
+        &Face,
+        &FaceNeighbours,
+        &mut FaceVertices,
+        Option<&Elevation>,
+    )>,
+    q_heights: Query<(Entity, &Face, Option<&Elevation>)>,
+    changed_elevations: Query<(Entity, &FaceNeighbours), Changed<Elevation>>,
+    mut removed_elevations: RemovedComponents<Elevation>,
+) {
+    // faces that lost their height could be anywhere, so those mean every chunk too
+    let relayout_all = settings.is_changed() || removed_elevations.any_removed();
+    if !relayout_all && changed_elevations.is_empty() {
+        return;
+    }
+
+    let radius_of = |elevation: Option<&Elevation>| settings.radius(elevation.map_or(0.0, |e| e.0));
+    let radii: HashMap<Entity, (&Face, f32)> = q_heights
+        .iter()
+        .map(|(entity_id, face, elevation)| (entity_id, (face, radius_of(elevation))))
+        .collect();
+
+    // a face's walls depend on its neighbours' heights as well as its own
+    let mut moved: Vec<Entity> = Vec::new();
+    for (entity_id, face_neighbours) in &changed_elevations {
+        moved.push(entity_id);
+        moved.extend(face_neighbours.iter());
+    }
+    let dirty_meshes: Vec<AssetId<Mesh>> = moved
+        .iter()
+        .filter_map(|entity_id| query_faces.get(*entity_id).ok())
+        .map(|(_, _, _, face_vertices, _)| face_vertices.mesh.id())
+        .collect();
+
+    // faces gain and lose walls as their neighbours rise and fall, so each chunk is laid out afresh
+    let mut chunks: HashMap<AssetId<Mesh>, Vec<_>> = HashMap::new();
+    for (_, face, face_neighbours, face_vertices, elevation) in &mut query_faces {
+        if !relayout_all && !dirty_meshes.contains(&face_vertices.mesh.id()) {
+            continue;
+        }
+        let radius = radius_of(elevation);
+        let floors = side_floors(
+            face,
+            radius,
+            face_neighbours
+                .iter()
+                .filter_map(|entity_id| radii.get(entity_id).copied()),
+        );
+        if let Some(mesh) = meshes.get_mut(&face_vertices.outline)
+            && let Some(VertexAttributeValues::Float32x3(positions)) =
+                mesh.attribute_mut(Mesh::ATTRIBUTE_POSITION)
+        {
+            let new_positions = build_outline(&face.vertices, radius);
+            positions[face_vertices.outline_range.clone()].copy_from_slice(&new_positions);
+        }
+        chunks.entry(face_vertices.mesh.id()).or_default().push((
+            face,
+            face_vertices,
+            radius,
+            floors,
+        ));
+    }
+
+    for (mesh_id, mut faces) in chunks {
+        let Some(mesh) = meshes.get_mut(mesh_id) else {
+            continue;
+        };
+        let Some(VertexAttributeValues::Float32x4(old_colours)) =
+            mesh.attribute(Mesh::ATTRIBUTE_COLOR)
+        else {
+            continue;
+        };
+        // keep the faces in the order they were laid out in, and their colours with them
+        faces.sort_unstable_by_key(|(_, face_vertices, ..)| face_vertices.range.start);
+        let mut positions = Vec::new();
+        let mut colours = Vec::new();
+        for (face, face_vertices, radius, floors) in &mut faces {
+            let colour = old_colours[face_vertices.range.start];
+            let start = positions.len();
+            positions.extend(build_face_positions(&face.vertices, *radius, floors));
+            colours.resize(positions.len(), colour);
+            let range = start..positions.len();
+            if face_vertices.range != range {
+                face_vertices.range = range;
+            }
+        }
+
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colours);
+        // lighting needs normals that match the new shape
+        mesh.compute_flat_normals();
+    }
+}
+
+/// V toggles relief, [ and ] nudge the exaggeration
+fn handle_relief_keys(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<ReliefSettings>,
+) {
+    if keyboard_input.just_pressed(KeyCode::KeyV) {
+        settings.enabled = !settings.enabled;
+    }
+    if keyboard_input.just_pressed(KeyCode::BracketLeft) {
+        settings.exaggeration = (settings.exaggeration - 10.0).max(MIN_EXAGGERATION);
+    }
+    if keyboard_input.just_pressed(KeyCode::BracketRight) {
+        settings.exaggeration = (settings.exaggeration + 10.0).min(MAX_EXAGGERATION);
+    }
+}
+
+fn setup_relief_ui(mut commands: Commands) {
+    commands
+        .spawn(Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(5.0),
+            left: Val::Px(5.0),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(4.0),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((Text::new(""), ReliefUiText));
+            parent
+                .spawn((
+                    Node {
+                        width: Val::Px(200.0),
+                        height: Val::Px(12.0),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+                    Interaction::default(),
+                    ExaggerationSlider,
+                ))
+                .observe(drag_slider::<Press>)
+                .observe(drag_slider::<Drag>)
+                .with_children(|track| {
+                    track.spawn((
+                        Node {
+                            height: Val::Percent(100.0),
+                            ..default()
+                        },
+                        BackgroundColor(Color::srgb(0.8, 0.8, 0.8)),
+                        ExaggerationSliderFill,
+                    ));
+                });
+        });
+}
+
+/// Set the exaggeration from where the pointer is along the slider track
+fn drag_slider<E: std::fmt::Debug + Clone + Reflect>(
+    event: On<Pointer<E>>,
+    track: Single<(&ComputedNode, &UiGlobalTransform), With<ExaggerationSlider>>,
+    mut settings: ResMut<ReliefSettings>,
+) {
+    let (node, transform) = *track;
+    // the node's layout is in physical pixels, the pointer is in logical ones
+    let centre = transform.translation * node.inverse_scale_factor();
+    let width = node.size().x * node.inverse_scale_factor();
+    let left = centre.x - width / 2.0;
+
+    let t = ((event.pointer_location.position.x - left) / width).clamp(0.0, 1.0);
+    settings.exaggeration = MIN_EXAGGERATION + t * (MAX_EXAGGERATION - MIN_EXAGGERATION);
+}
+
+fn update_relief_ui(
+    settings: Res<ReliefSettings>,
+    mut q_text: Query<&mut Text, With<ReliefUiText>>,
+    mut q_fill: Query<&mut Node, With<ExaggerationSliderFill>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    for mut text in &mut q_text {
+        text.0 = format!(
+            "Relief (V): {}  exaggeration ([ ]): {:.0}x",
+            if settings.enabled { "on" } else { "off" },
+            settings.exaggeration
+        );
+    }
+    for mut node in &mut q_fill {
+        node.width = Val::Percent(
+            100.0 * (settings.exaggeration - MIN_EXAGGERATION)
+                / (MAX_EXAGGERATION - MIN_EXAGGERATION),
+        );
+    }
+}
+
+pub struct ReliefPlugin;
+
+impl Plugin for ReliefPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReliefSettings>()
+            .add_systems(Startup, setup_relief_ui)
+            .add_systems(
+                Update,
+                (
+                    hold_camera_while_pressed::<ExaggerationSlider>,
+                    handle_relief_keys,
+                    apply_relief,
+                    update_relief_ui,
+                )
+                    .chain(),
+            );
+    }
+}
@@ -0,0 +1,36 @@
+// systems that redo their work when components go missing watch for removals of several
+// components at once
+
+use bevy::prelude::*;
+
+/// Removal readers that can say whether anything was removed since they last looked
+pub trait AnyRemoved {
+    /// Read every reader, so none of them carry stale removals into the next frame,
+    /// and say whether any of them saw a removal
+    fn any_removed(&mut self) -> bool;
+}
+
+impl<T: Component> AnyRemoved for RemovedComponents<'_, '_, T> {
+    fn any_removed(&mut self) -> bool {
+        self.read().count() > 0
+    }
+}
+
+macro_rules! impl_any_removed {
+    ($($reader:ident $index:tt),+) => {
+        impl<$($reader: AnyRemoved),+> AnyRemoved for ($($reader,)+) {
+            fn any_removed(&mut self) -> bool {
+                // no short circuit, every reader has to be read
+                let mut any = false;
+                $(any |= self.$index.any_removed();)+
+                any
+            }
+        }
+    };
+}
+
+impl_any_removed!(A 0, B 1);
+impl_any_removed!(A 0, B 1, C 2);
+impl_any_removed!(A 0, B 1, C 2, D 3);
+impl_any_removed!(A 0, B 1, C 2, D 3, E 4);
+impl_any_removed!(A 0, B 1, C 2, D 3, E 4, F 5);
@@ -0,0 +1,521 @@
+// save whole worlds to disk and load them back, faces are keyed by their subsphere index
+// so a save still lines up with a globe built fresh from the same config
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{fmt, fs, path::Path, path::PathBuf};
+
+use crate::config::{ConfigError, WorldGenConfig};
+use crate::crust::{CrustThickness, CrustType};
+use crate::elevation::Elevation;
+use crate::growth::GrowthBias;
+use crate::sea_level::SeaLevel;
+use crate::seed::{RngStream, WorldRng, WorldSeed};
+use crate::setup::{Face, FaceNeighbours, PlatePalette, rebuild_globe};
+use crate::simulation::{SavedSimulation, SimulationProgress, restore_simulation};
+use crate::states::{GameState, SimulationState, WorldGenState};
+use crate::worldgen::{
+    BoundaryClass, FacePlateVelocity, Land, Plate, PlateBoundary, PlateGenFrontier, Sea,
+};
+
+/// Bump this whenever the layout of `SaveFile` changes, older saves are refused rather than misread
+const SAVE_VERSION: u32 = 1;
+
+/// Everything needed to put a world back exactly as it was
+#[derive(Serialize, Deserialize)]
+pub struct SaveFile {
+    version: u32,
+    seed: u64,
+    config: WorldGenConfig,
+    /// Land and sea aren't saved, they come back from the heights and this
+    sea_level: f32,
+    /// How far each random stream had been drawn, so rerunning a stage after loading
+    /// gives what it would have without the save and load in between
+    rng: Vec<(RngStream, u64)>,
+    /// Which plate as seeded each plate took its colour from, the colours come from the seed
+    palette_order: Vec<usize>,
+    /// What each plate was dealt to grow by, needed to carry on growing plates after a load
+    growth_bias: GrowthBias,
+    state: SavedState,
+    simulation: SavedSimulation,
+    faces: Vec<SavedFace>,
+}
+
+/// Where the app was when the world was saved
+#[derive(Serialize, Deserialize)]
+enum SavedState {
+    WorldGen(WorldGenState),
+    Simulation(SimulationState),
+}
+
+/// The components a face had, `None` or `false` for anything not generated yet
+#[derive(Serialize, Deserialize)]
+struct SavedFace {
+    index: usize,
+    plate: Option<usize>,
+    frontier: bool,
+    boundary: bool,
+    crust: Option<CrustType>,
+    crust_thickness: Option<f32>,
+    velocity: Option<[f32; 3]>,
+    /// Class of each classified boundary side, by the index of the face across it
+    boundary_classes: Vec<(usize, BoundaryClass)>,
+    elevation: Option<f32>,
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    Write(ron::Error),
+    Version(u32),
+    Config(ConfigError),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "could not access save file: {e}"),
+            Self::Parse(e) => write!(f, "could not parse save file: {e}"),
+            Self::Write(e) => write!(f, "could not write save file: {e}"),
+            Self::Version(version) => write!(
+                f,
+                "save file is version {version}, this build only reads version {SAVE_VERSION}"
+            ),
+            Self::Config(e) => write!(f, "save file has an {e}"),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl SaveFile {
+    /// Read a save, checking it's a version we understand and its config is usable
+    pub fn read(path: &Path) -> Result<Self, SaveError> {
+        let contents = fs::read_to_string(path).map_err(SaveError::Io)?;
+        let save: Self = ron::from_str(&contents).map_err(SaveError::Parse)?;
+        if save.version != SAVE_VERSION {
+            return Err(SaveError::Version(save.version));
+        }
+        save.config.validate().map_err(SaveError::Config)?;
+        Ok(save)
+    }
+
+    fn write(&self, path: &Path) -> Result<(), SaveError> {
+        let contents = ron::to_string(self).map_err(SaveError::Write)?;
+        fs::write(path, contents).map_err(SaveError::Io)
+    }
+
+    pub fn seed(&self) -> WorldSeed {
+        WorldSeed(self.seed)
+    }
+
+    pub fn config(&self) -> &WorldGenConfig {
+        &self.config
+    }
+}
+
+/// A save waiting to be loaded at the start of the next frame, when nothing else is touching the faces
+#[derive(Resource)]
+pub struct PendingLoad(pub Option<SaveFile>);
+
+#[derive(Event)]
+pub struct SaveWorld {
+    pub path: PathBuf,
+}
+
+/// Replace the current world with the one in a save, rebuilding the globe to match it
+#[derive(Event)]
+pub struct LoadWorld {
+    pub path: PathBuf,
+}
+
+/// What the world was generated from, and how far through its random streams it has got
+#[derive(SystemParam)]
+struct WorldOrigin<'w> {
+    seed: Res<'w, WorldSeed>,
+    config: Res<'w, WorldGenConfig>,
+    rng: Res<'w, WorldRng>,
+    growth_bias: Res<'w, GrowthBias>,
+}
+
+/// Whichever states the app is in, only one of the sub states exists at a time
+#[derive(SystemParam)]
+struct AppStates<'w> {
+    game: Res<'w, State<GameState>>,
+    worldgen: Option<Res<'w, State<WorldGenState>>>,
+    simulation: Option<Res<'w, State<SimulationState>>>,
+}
+
+impl AppStates<'_> {
+    fn saved(&self) -> SavedState {
+        match self.game.get() {
+            GameState::WorldGen => SavedState::WorldGen(
+                self.worldgen
+                    .as_ref()
+                    .map_or_else(default, |state| state.get().clone()),
+            ),
+            GameState::Simulation => SavedState::Simulation(
+                self.simulation
+                    .as_ref()
+                    .map_or_else(default, |state| state.get().clone()),
+            ),
+        }
+    }
+}
+
+fn save_world(
+    save: On<SaveWorld>,
+    origin: WorldOrigin,
+    sea_level: Res<SeaLevel>,
+    states: AppStates,
+    simulation: SimulationProgress,
+    palette: Res<PlatePalette>,
+    query_faces: Query<(
+        &Face,
+        Option<&Plate>,
+        Has<PlateGenFrontier>,
+        Option<&PlateBoundary>,
+        Option<&CrustType>,
+        Option<&CrustThickness>,
+        Option<&FacePlateVelocity>,
+        Option<&Elevation>,
+    )>,
+) {
+    let mut faces: Vec<SavedFace> = query_faces
+        .iter()
+        .map(
+            |(face, plate, frontier, boundary, crust, thickness, velocity, elevation)| {
+                let boundary_classes = boundary
+                    .iter()
+                    .flat_map(|boundary| &boundary.sides)
+                    .filter_map(|side| {
+                        let (across, ..) = query_faces.get(side.across).ok()?;
+                        Some((across.index, side.class?))
+                    })
+                    .collect();
+                SavedFace {
+                    index: face.index,
+                    plate: plate.map(|plate| plate.0),
+                    frontier,
+                    boundary: boundary.is_some(),
+                    crust: crust.copied(),
+                    crust_thickness: thickness.map(|thickness| thickness.0),
+                    velocity: velocity.map(|velocity| velocity.velocity.to_array()),
+                    boundary_classes,
+                    elevation: elevation.map(|elevation| elevation.0),
+                }
+            },
+        )
+        .collect();
+    faces.sort_unstable_by_key(|face| face.index);
+
+    let file = SaveFile {
+        version: SAVE_VERSION,
+        seed: origin.seed.0,
+        config: origin.config.clone(),
+        sea_level: sea_level.level,
+        rng: origin.rng.positions(),
+        palette_order: palette.order().to_vec(),
+        growth_bias: origin.growth_bias.clone(),
+        state: states.saved(),
+        simulation: simulation.saved(),
+        faces,
+    };
+
+    match file.write(&save.path) {
+        Ok(()) => info!("Saved world to {}", save.path.display()),
+        Err(e) => error!("Could not save world to {}: {e}", save.path.display()),
+    }
+}
+
+fn load_world(load: On<LoadWorld>, mut commands: Commands) {
+    match SaveFile::read(&load.path) {
+        Ok(save) => {
+            info!("Loading world from {}", load.path.display());
+            commands.insert_resource(PendingLoad(Some(save)));
+        }
+        Err(e) => error!("Could not load world from {}: {e}", load.path.display()),
+    }
+}
+
+fn load_pending(world: &mut World) {
+    let Some(save) = world
+        .get_resource_mut::<PendingLoad>()
+        .and_then(|mut pending| pending.0.take())
+    else {
+        return;
+    };
+
+    world.insert_resource(save.seed());
+    world.insert_resource(save.config.clone());
+    let mut sea_level = world.get_resource_or_init::<SeaLevel>();
+    sea_level.level = save.sea_level;
+    sea_level.target_land = save.config.land_fraction;
+    rebuild_globe(world);
+    world.resource_mut::<WorldRng>().resume(&save.rng);
+    world
+        .resource_mut::<PlatePalette>()
+        .reorder(&save.palette_order);
+    world.insert_resource(save.growth_bias.clone());
+    restore_simulation(world, &save.simulation);
+    apply_save(world, &save);
+}
+
+/// Put the saved components back on the faces of a globe built from the save's config
+fn apply_save(world: &mut World, save: &SaveFile) {
+    let mut face_entities = Vec::new();
+    for (entity_id, face) in world.query::<(Entity, &Face)>().iter(world) {
+        if face.index >= face_entities.len() {
+            face_entities.resize(face.index + 1, Entity::PLACEHOLDER);
+        }
+        face_entities[face.index] = entity_id;
+    }
+
+    let sea_level = SeaLevel {
+        level: save.sea_level,
+        target_land: save.config.land_fraction,
+    };
+    let mut boundary_faces = Vec::new();
+    for saved in &save.faces {
+        let Some(&entity_id) = face_entities
+            .get(saved.index)
+            .filter(|&&entity_id| entity_id != Entity::PLACEHOLDER)
+        else {
+            warn!("Save has face {} but the globe doesn't", saved.index);
+            continue;
+        };
+
+        let mut entity = world.entity_mut(entity_id);
+        if let Some(plate) = saved.plate.map(Plate) {
+            entity.insert(plate);
+        }
+        if saved.frontier {
+            entity.insert(PlateGenFrontier);
+        }
+        if saved.boundary {
+            boundary_faces.push((entity_id, &saved.boundary_classes));
+        }
+        if let Some(crust) = saved.crust {
+            entity.insert(crust);
+        }
+        if let Some(thickness) = saved.crust_thickness {
+            entity.insert(CrustThickness(thickness));
+        }
+        if let Some(velocity) = saved.velocity {
+            entity.insert(FacePlateVelocity {
+                velocity: Vec3::from_array(velocity),
+            });
+        }
+        if let Some(elevation) = saved.elevation {
+            entity.insert(Elevation(elevation));
+        }
+        // put land and sea straight back too, anything that runs as the load finishes wants them
+        let elevation = saved.elevation.map(Elevation);
+        match sea_level.is_land(elevation.as_ref(), saved.crust.as_ref()) {
+            Some(true) => {
+                entity.insert(Land);
+            }
+            Some(false) => {
+                entity.insert(Sea);
+            }
+            None => {}
+        }
+    }
+
+    // only whether a face was on a boundary and the classes of its sides are saved,
+    // the sides themselves come from the plates around it
+    let mut q_faces = world.query::<(&Face, &FaceNeighbours, &Plate)>();
+    let mut q_neighbours = world.query::<(&Face, &Plate)>();
+    let boundaries: Vec<(Entity, PlateBoundary)> = boundary_faces
+        .into_iter()
+        .filter_map(|(entity_id, classes)| {
+            let (face, face_neighbours, plate) = q_faces.get(world, entity_id).ok()?;
+            let neighbours =
+                face_neighbours.iter().filter_map(|&neighbour_id| {
+                    q_neighbours.get(world, neighbour_id).ok().map(
+                        |(neighbour, neighbour_plate)| (neighbour_id, neighbour, neighbour_plate.0),
+                    )
+                });
+            let mut boundary = PlateBoundary::between(face, plate.0, neighbours)?;
+            for side in &mut boundary.sides {
+                let across = q_neighbours.get(world, side.across).ok()?.0.index;
+                side.class = classes
+                    .iter()
+                    .find(|(index, _)| *index == across)
+                    .map(|(_, class)| *class);
+            }
+            Some((entity_id, boundary))
+        })
+        .collect();
+    for (entity_id, boundary) in boundaries {
+        world.entity_mut(entity_id).insert(boundary);
+    }
+
+    match &save.state {
+        SavedState::WorldGen(state) => {
+            world
+                .resource_mut::<NextState<GameState>>()
+                .set(GameState::WorldGen);
+            world
+                .resource_mut::<NextState<WorldGenState>>()
+                .set(state.clone());
+        }
+        SavedState::Simulation(state) => {
+            world
+                .resource_mut::<NextState<GameState>>()
+                .set(GameState::Simulation);
+            world
+                .resource_mut::<NextState<SimulationState>>()
+                .set(state.clone());
+        }
+    }
+}
+
+/// F5 saves the world to the working directory, F9 loads it back
+fn handle_save_keys(mut commands: Commands, keyboard_input: Res<ButtonInput<KeyCode>>) {
+    if keyboard_input.just_pressed(KeyCode::F5) {
+        commands.trigger(SaveWorld {
+            path: "world.ron".into(),
+        });
+    }
+    if keyboard_input.just_pressed(KeyCode::F9) {
+        commands.trigger(LoadWorld {
+            path: "world.ron".into(),
+        });
+    }
+}
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_observer(save_world)
+            .add_observer(load_world)
+            .add_systems(First, load_pending)
+            .add_systems(
+                Update,
+                handle_save_keys.run_if(resource_exists::<ButtonInput<KeyCode>>),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GrowthStrategy;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    fn sample_save() -> SaveFile {
+        SaveFile {
+            version: SAVE_VERSION,
+            seed: 42,
+            config: WorldGenConfig::default(),
+            sea_level: -1.25,
+            rng: vec![(RngStream::PlateSeeds, 16), (RngStream::Continents, 64)],
+            palette_order: vec![0, 2, 1],
+            growth_bias: sample_bias(),
+            state: SavedState::Simulation(SimulationState::default()),
+            simulation: ron::from_str("(elapsed_myr: 12.5, speed: 2, turned: [(0, 0.25)])")
+                .unwrap(),
+            faces: vec![
+                SavedFace {
+                    index: 0,
+                    plate: Some(1),
+                    frontier: false,
+                    boundary: true,
+                    crust: Some(CrustType::Continental),
+                    crust_thickness: Some(32.0),
+                    velocity: Some([0.1, -0.2, 0.0]),
+                    boundary_classes: vec![(
+                        1,
+                        BoundaryClass {
+                            kind: crate::worldgen::BoundaryKind::Convergent,
+                            magnitude: 0.5,
+                        },
+                    )],
+                    elevation: Some(2.5),
+                },
+                SavedFace {
+                    index: 1,
+                    plate: None,
+                    frontier: true,
+                    boundary: false,
+                    crust: None,
+                    crust_thickness: None,
+                    velocity: None,
+                    boundary_classes: Vec::new(),
+                    elevation: None,
+                },
+            ],
+        }
+    }
+
+    fn sample_bias() -> GrowthBias {
+        let strategies = [
+            GrowthStrategy::PowerLaw { exponent: 2.0 },
+            GrowthStrategy::Directional { strength: 3.0 },
+            GrowthStrategy::Noise {
+                scale: 2.0,
+                strength: 4.0,
+            },
+        ];
+        GrowthBias::new(&strategies, 3, &mut ChaCha8Rng::seed_from_u64(7))
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("hex_globe_{name}_{}.ron", std::process::id()))
+    }
+
+    #[test]
+    fn save_file_round_trips() {
+        let path = temp_path("round_trip");
+        let save = sample_save();
+        save.write(&path).unwrap();
+        let loaded = SaveFile::read(&path);
+        fs::remove_file(&path).unwrap();
+
+        let loaded = loaded.unwrap();
+        assert_eq!(
+            ron::to_string(&loaded).unwrap(),
+            ron::to_string(&save).unwrap()
+        );
+    }
+
+    #[test]
+    fn growth_bias_survives_a_save() {
+        let path = temp_path("growth_bias");
+        sample_save().write(&path).unwrap();
+        let loaded = SaveFile::read(&path);
+        fs::remove_file(&path).unwrap();
+
+        let (bias, loaded) = (sample_bias(), loaded.unwrap().growth_bias);
+        assert!(!loaded.is_uniform());
+        let (from, to) = (
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.99, 0.1, 0.0).normalize(),
+        );
+        for plate in 0..3 {
+            assert_eq!(
+                loaded.weight(plate, from, to).to_bits(),
+                bias.weight(plate, from, to).to_bits()
+            );
+        }
+    }
+
+    #[test]
+    fn other_versions_are_refused() {
+        let path = temp_path("version");
+        let save = SaveFile {
+            version: SAVE_VERSION + 1,
+            ..sample_save()
+        };
+        save.write(&path).unwrap();
+        let loaded = SaveFile::read(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(loaded, Err(SaveError::Version(version)) if version == SAVE_VERSION + 1));
+    }
+}
@@ -0,0 +1,402 @@
+// land is whatever stands above the sea, so moving the sea level floods or drains the coasts,
+// until faces have heights continental crust stands in for land
+
+use bevy::prelude::*;
+use bevy::ui::RelativeCursorPosition;
+
+use crate::config::WorldGenConfig;
+use crate::crust::CrustType;
+use crate::elevation::Elevation;
+use crate::removed::AnyRemoved;
+use crate::states::WorldGenState;
+use crate::ui::hold_camera_while_pressed;
+use crate::worldgen::{Land, Sea};
+
+/// Lowest and highest the slider can put the sea, in km
+const SLIDER_RANGE: (f32, f32) = (-4.0, 4.0);
+const SLIDER_WIDTH: f32 = 200.0;
+const TRACK_COLOUR: Color = Color::srgba(0.1, 0.1, 0.1, 0.8);
+const HANDLE_COLOUR: Color = Color::srgb(0.0, 0.412, 0.58);
+const BUTTON_COLOUR: Color = Color::srgba(0.1, 0.1, 0.1, 0.8);
+/// How far each press of the target buttons moves the target
+const TARGET_STEP: f32 = 0.05;
+
+/// Height of the sea in km, faces higher than it are land
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct SeaLevel {
+    pub level: f32,
+    /// Share of the faces that fitting the sea level aims to leave as land
+    pub target_land: f32,
+}
+
+impl FromWorld for SeaLevel {
+    // aim for as much land as there's continental crust
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            level: 0.0,
+            target_land: world
+                .get_resource::<WorldGenConfig>()
+                .map_or(WorldGenConfig::default().land_fraction, |config| {
+                    config.land_fraction
+                }),
+        }
+    }
+}
+
+impl SeaLevel {
+    /// The sea level that leaves `land_fraction` of these heights above it, `None` without any
+    pub fn solve(elevations: impl IntoIterator<Item = f32>, land_fraction: f32) -> Option<f32> {
+        let mut elevations: Vec<f32> = elevations.into_iter().collect();
+        elevations.sort_unstable_by(|a, b| b.total_cmp(a));
+        let n = elevations.len();
+        #[allow(clippy::cast_precision_loss, clippy::cast_sign_loss)]
+        let n_land = ((n as f32 * land_fraction).round() as usize).min(n);
+
+        // halfway between the lowest face left dry and the highest one flooded
+        match n_land {
+            _ if n == 0 => None,
+            0 => Some(elevations[0]),
+            _ if n_land == n => Some(elevations[n - 1].next_down()),
+            _ => Some(f32::midpoint(elevations[n_land - 1], elevations[n_land])),
+        }
+    }
+
+    /// Whether a face is land, from its height once it has one and its crust until then
+    pub fn is_land(self, elevation: Option<&Elevation>, crust: Option<&CrustType>) -> Option<bool> {
+        match (elevation, crust) {
+            (Some(elevation), _) => Some(elevation.0 > self.level),
+            (None, Some(crust)) => Some(*crust == CrustType::Continental),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Move the sea level so `target_land` of the faces with heights are land
+#[derive(Event)]
+pub struct FitSeaLevel;
+
+fn fit_sea_level(
+    _fit: On<FitSeaLevel>,
+    mut sea_level: ResMut<SeaLevel>,
+    q_elevations: Query<&Elevation>,
+) {
+    let target = sea_level.target_land;
+    if let Some(level) = SeaLevel::solve(q_elevations.iter().map(|e| e.0), target) {
+        sea_level.level = level;
+        info!(
+            "Sea level {level:.2} km leaves {:.0}% of the globe as land",
+            100.0 * target
+        );
+    }
+}
+
+/// Fit the sea level to the target as soon as the heights are generated
+fn fit_generated_sea_level(mut commands: Commands) {
+    commands.trigger(FitSeaLevel);
+}
+
+/// Removing any of these can turn land into sea or back
+type RemovedSurfaceData<'w, 's> = (
+    RemovedComponents<'w, 's, Elevation>,
+    RemovedComponents<'w, 's, CrustType>,
+);
+
+type SurfaceChanged = Or<(Changed<Elevation>, Changed<CrustType>)>;
+
+/// Keep `Land` and `Sea` in step with the sea level, writing only the faces that flip
+fn apply_sea_level(
+    mut commands: Commands,
+    sea_level: Res<SeaLevel>,
+    query_faces: Query<(
+        Entity,
+        Option<&Elevation>,
+        Option<&CrustType>,
+        Has<Land>,
+        Has<Sea>,
+    )>,
+    changed_faces: Query<(), SurfaceChanged>,
+    mut removed: RemovedSurfaceData,
+) {
+    if !removed.any_removed() && !sea_level.is_changed() && changed_faces.is_empty() {
+        return;
+    }
+
+    for (entity_id, elevation, crust, land, sea) in query_faces.iter() {
+        let mut entity = commands.entity(entity_id);
+        match sea_level.is_land(elevation, crust) {
+            Some(true) if !land || sea => {
+                entity.insert(Land).remove::<Sea>();
+            }
+            Some(false) if land || !sea => {
+                entity.insert(Sea).remove::<Land>();
+            }
+            None if land || sea => {
+                entity.remove::<(Land, Sea)>();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Keeps land and sea following the sea level, with or without a window
+pub struct SeaLevelPlugin;
+
+impl Plugin for SeaLevelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SeaLevel>()
+            .add_observer(fit_sea_level)
+            .add_systems(
+                OnTransition {
+                    exited: WorldGenState::GenElevation,
+                    entered: WorldGenState::JustChill,
+                },
+                fit_generated_sea_level,
+            )
+            .add_systems(Update, apply_sea_level);
+    }
+}
+
+#[derive(Component)]
+struct SeaLevelUi;
+
+#[derive(Component)]
+struct SeaLevelUiText;
+
+#[derive(Component)]
+struct SeaLevelSlider;
+
+#[derive(Component)]
+struct SeaLevelHandle;
+
+#[derive(Component, Clone, Copy)]
+enum SeaLevelControl {
+    LowerTarget,
+    RaiseTarget,
+    Fit,
+}
+
+impl SeaLevelControl {
+    const ALL: [SeaLevelControl; 3] = [
+        SeaLevelControl::LowerTarget,
+        SeaLevelControl::RaiseTarget,
+        SeaLevelControl::Fit,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            SeaLevelControl::LowerTarget => "-",
+            SeaLevelControl::RaiseTarget => "+",
+            SeaLevelControl::Fit => "Fit",
+        }
+    }
+}
+
+fn setup_sea_level_ui(mut commands: Commands) {
+    commands
+        .spawn((
+            // on the right, clear of the rewind help above and the stage text below
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Percent(40.0),
+                right: Val::Px(5.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::End,
+                row_gap: Val::Px(4.0),
+                display: Display::None,
+                ..default()
+            },
+            SeaLevelUi,
+        ))
+        .with_children(|parent| {
+            parent.spawn((Text::new(""), SeaLevelUiText));
+            parent
+                .spawn((
+                    Button,
+                    Node {
+                        width: Val::Px(SLIDER_WIDTH),
+                        height: Val::Px(12.0),
+                        ..default()
+                    },
+                    BackgroundColor(TRACK_COLOUR),
+                    RelativeCursorPosition::default(),
+                    SeaLevelSlider,
+                ))
+                .with_child((
+                    Node {
+                        position_type: PositionType::Absolute,
+                        width: Val::Px(6.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    BackgroundColor(HANDLE_COLOUR),
+                    SeaLevelHandle,
+                ));
+            parent
+                .spawn(Node {
+                    column_gap: Val::Px(4.0),
+                    ..default()
+                })
+                .with_children(|row| {
+                    for control in SeaLevelControl::ALL {
+                        row.spawn((
+                            Button,
+                            Node {
+                                padding: UiRect::axes(Val::Px(8.0), Val::Px(4.0)),
+                                ..default()
+                            },
+                            BackgroundColor(BUTTON_COLOUR),
+                            control,
+                        ))
+                        .with_child(Text::new(control.label()));
+                    }
+                });
+        });
+}
+
+/// Dragging along the slider sets the sea level directly, the buttons move the target land
+/// share and fit the sea level to it
+fn handle_sea_level_controls(
+    mut commands: Commands,
+    mut sea_level: ResMut<SeaLevel>,
+    q_slider: Query<(&Interaction, &RelativeCursorPosition), With<SeaLevelSlider>>,
+    q_buttons: Query<(&Interaction, &SeaLevelControl), Changed<Interaction>>,
+) {
+    for (interaction, cursor) in &q_slider {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        if let Some(cursor) = cursor.normalized {
+            // normalized runs from -0.5 to 0.5 across the node
+            let t = (cursor.x + 0.5).clamp(0.0, 1.0);
+            let level = SLIDER_RANGE.0.lerp(SLIDER_RANGE.1, t);
+            let target_land = sea_level.target_land;
+            sea_level.set_if_neq(SeaLevel { level, target_land });
+        }
+    }
+    for (interaction, control) in &q_buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match control {
+            SeaLevelControl::LowerTarget => {
+                sea_level.target_land = (sea_level.target_land - TARGET_STEP).max(0.0);
+            }
+            SeaLevelControl::RaiseTarget => {
+                sea_level.target_land = (sea_level.target_land + TARGET_STEP).min(1.0);
+            }
+            SeaLevelControl::Fit => commands.trigger(FitSeaLevel),
+        }
+    }
+}
+
+type SeaLevelUiChanged = Or<(Changed<Elevation>, Added<Land>)>;
+
+/// Only shown once there are heights for the sea to cover
+fn update_sea_level_ui(
+    sea_level: Res<SeaLevel>,
+    changed_faces: Query<(), SeaLevelUiChanged>,
+    mut removed: (RemovedComponents<Elevation>, RemovedComponents<Land>),
+    q_land: Query<Has<Land>, With<Elevation>>,
+    mut q_panel: Query<&mut Node, (With<SeaLevelUi>, Without<SeaLevelHandle>)>,
+    mut q_handle: Query<&mut Node, With<SeaLevelHandle>>,
+    mut q_text: Query<&mut Text, With<SeaLevelUiText>>,
+) {
+    if !removed.any_removed() && !sea_level.is_changed() && changed_faces.is_empty() {
+        return;
+    }
+
+    let (faces, land) = q_land.iter().fold((0, 0), |(faces, land), is_land| {
+        (faces + 1, land + usize::from(is_land))
+    });
+    for mut node in &mut q_panel {
+        let display = if faces > 0 {
+            Display::Flex
+        } else {
+            Display::None
+        };
+        if node.display != display {
+            node.display = display;
+        }
+    }
+    if faces == 0 {
+        return;
+    }
+
+    let t = (sea_level.level - SLIDER_RANGE.0) / (SLIDER_RANGE.1 - SLIDER_RANGE.0);
+    let left = Val::Percent(100.0 * t.clamp(0.0, 1.0));
+    for mut node in &mut q_handle {
+        if node.left != left {
+            node.left = left;
+        }
+    }
+    #[allow(clippy::cast_precision_loss)]
+    let share = 100.0 * land as f32 / faces as f32;
+    let label = format!(
+        "Sea level {:+.2} km, {share:.1}% land\nFit to {:.0}% land",
+        sea_level.level,
+        100.0 * sea_level.target_land
+    );
+    for mut text in &mut q_text {
+        if text.0 != label {
+            text.0.clone_from(&label);
+        }
+    }
+}
+
+/// A slider for the sea level and buttons to fit it to a share of land
+pub struct SeaLevelUiPlugin;
+
+impl Plugin for SeaLevelUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_sea_level_ui).add_systems(
+            Update,
+            (
+                hold_camera_while_pressed::<SeaLevelSlider>,
+                handle_sea_level_controls,
+                update_sea_level_ui,
+            )
+                .chain(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_heights_no_sea_level() {
+        assert_eq!(SeaLevel::solve([], 0.5), None);
+    }
+
+    #[test]
+    fn no_land_puts_the_sea_at_the_highest_face() {
+        assert_eq!(SeaLevel::solve([1.0, 3.0, 2.0], 0.0), Some(3.0));
+    }
+
+    #[test]
+    fn all_land_puts_the_sea_just_under_the_lowest_face() {
+        assert_eq!(
+            SeaLevel::solve([1.0, 3.0, 2.0], 1.0),
+            Some(1.0_f32.next_down())
+        );
+    }
+
+    #[test]
+    fn sea_sits_halfway_between_land_and_sea() {
+        assert_eq!(SeaLevel::solve([4.0, 1.0, 3.0, 2.0], 0.5), Some(2.5));
+    }
+
+    #[test]
+    fn faces_at_the_same_height_flood_together() {
+        // the share can't split faces of the same height, so they all end up under the sea
+        let level = SeaLevel::solve([2.0, 1.0, 1.0, 1.0], 0.5);
+        assert_eq!(level, Some(1.0));
+        let level = level.unwrap();
+        let land = [2.0, 1.0, 1.0, 1.0]
+            .iter()
+            .filter(|height| **height > level)
+            .count();
+        assert_eq!(land, 1);
+    }
+}
@@ -0,0 +1,93 @@
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+/// The seed every random decision in world generation is derived from
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WorldSeed(pub u64);
+
+impl WorldSeed {
+    /// Picks a fresh seed, for when none was given on the command line
+    pub fn random() -> Self {
+        Self(rand::rng().random())
+    }
+}
+
+/// Independent random streams, one per stage of generation.
+/// Each stage draws from its own stream so re-running one stage
+/// doesn't shift the numbers any other stage sees.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum RngStream {
+    Palette,
+    PlateSeeds,
+    PlateGrowth,
+    Continents,
+    PlateVelocities,
+    /// Per-plate growth biases, drawn once when the plates are seeded
+    GrowthBias,
+    PartitionWarp,
+}
+
+#[derive(Resource)]
+pub struct WorldRng {
+    seed: u64,
+    streams: HashMap<RngStream, ChaCha8Rng>,
+}
+
+impl WorldRng {
+    pub fn new(seed: WorldSeed) -> Self {
+        Self {
+            seed: seed.0,
+            streams: HashMap::default(),
+        }
+    }
+
+    /// Get the generator for a stage, creating it on first use
+    pub fn stream(&mut self, stream: RngStream) -> &mut ChaCha8Rng {
+        let seed = self.seed;
+        self.streams.entry(stream).or_insert_with(|| {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            rng.set_stream(stream as u64);
+            rng
+        })
+    }
+
+    /// How far through each stream used so far the draws have got, in words
+    pub fn positions(&self) -> Vec<(RngStream, u64)> {
+        // RON can't write a u128, and nothing draws anywhere near 2^64 words from one stream
+        let mut positions: Vec<_> = self
+            .streams
+            .iter()
+            .map(|(stream, rng)| {
+                let position = u64::try_from(rng.get_word_pos()).unwrap_or(u64::MAX);
+                (*stream, position)
+            })
+            .collect();
+        positions.sort_unstable_by_key(|(stream, _)| *stream as u64);
+        positions
+    }
+
+    /// Carry each stream on from where `positions` says it had got to
+    pub fn resume(&mut self, positions: &[(RngStream, u64)]) {
+        for (stream, position) in positions {
+            self.stream(*stream).set_word_pos(u128::from(*position));
+        }
+    }
+}
+
+impl FromWorld for WorldRng {
+    fn from_world(world: &mut World) -> Self {
+        let seed = *world.get_resource_or_insert_with(WorldSeed::random);
+        Self::new(seed)
+    }
+}
+
+pub struct SeedPlugin;
+
+impl Plugin for SeedPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldRng>();
+    }
+}
@@ -1,40 +1,125 @@
 use bevy::asset::RenderAssetUsages;
 use bevy::prelude::*;
 use bevy::render::render_resource::PrimitiveTopology;
-use rand::{Rng, rngs::ThreadRng};
-use std::num::NonZero;
+use rand::Rng;
+use std::{num::NonZero, ops::Index, ops::Range};
 use subsphere::prelude::*;
+use subsphere::proj::BaseTriProjector;
 
-pub const N_PLATES: usize = 40;
+use crate::config::{Projector, WorldGenConfig};
+use crate::seed::{RngStream, WorldRng};
 
-#[derive(Resource, Deref)]
-pub struct PlatePalette(Vec<Color>);
+/// Faces per merged mesh, keeps each vertex buffer a sensible size on large globes
+const FACES_PER_CHUNK: usize = 4096;
+
+/// A colour for each plate. The colours are dealt from the seed once per plate as seeded, and
+/// plates keep theirs through being renumbered, so a save only needs to keep the order
+#[derive(Resource)]
+pub struct PlatePalette {
+    colours: Vec<Color>,
+    /// The seeded plate whose colour each plate has, empty while none have been renumbered
+    order: Vec<usize>,
+}
+
+impl PlatePalette {
+    /// Shuffle the colours along with the plates when they're renumbered,
+    /// plate `i` gets the colour plate `order[i]` had
+    pub fn reorder(&mut self, order: &[usize]) {
+        self.order = order
+            .iter()
+            .map(|plate| self.order.get(*plate).copied().unwrap_or(*plate))
+            .collect();
+    }
+
+    /// Back to the colours as dealt, for plates numbered afresh
+    pub fn reset_order(&mut self) {
+        self.order.clear();
+    }
+
+    pub fn order(&self) -> &[usize] {
+        &self.order
+    }
+}
+
+impl Index<usize> for PlatePalette {
+    type Output = Color;
+
+    fn index(&self, plate: usize) -> &Color {
+        &self.colours[self.order.get(plate).copied().unwrap_or(plate)]
+    }
+}
 
 #[derive(Component)]
 pub struct Face {
+    /// Index of this face in the subsphere tessellation
+    pub index: usize,
     pub centre_pos: Vec3,
+    /// Corners of the face on the unit sphere, in subsphere winding order
+    pub vertices: Vec<Vec3>,
+}
+
+impl Face {
+    /// Area of the face on the unit sphere, near enough for faces this small and flat
+    pub fn area(&self) -> f32 {
+        let v0 = self.vertices[0];
+        self.vertices[1..]
+            .windows(2)
+            .map(|pair| 0.5 * (pair[0] - v0).cross(pair[1] - v0).length())
+            .sum()
+    }
+
+    /// Ends of the side this face shares with `other`, `None` if they don't touch
+    pub fn shared_side(&self, other: &Face) -> Option<(Vec3, Vec3)> {
+        // neighbours work their corners out separately, so allow for a little rounding
+        let mut shared = self.vertices.iter().filter(|vertex| {
+            other
+                .vertices
+                .iter()
+                .any(|corner| vertex.distance_squared(*corner) < 1e-10)
+        });
+        Some((*shared.next()?, *shared.next()?))
+    }
 }
 
 #[derive(Component, Deref)]
 pub struct FaceNeighbours(Vec<Entity>);
 
+/// Where a face's vertices live inside the merged globe meshes it belongs to
 #[derive(Component)]
-pub struct ChangeColour {
-    pub colour: Color,
+pub struct FaceVertices {
+    pub mesh: Handle<Mesh>,
+    pub range: Range<usize>,
+    pub outline: Handle<Mesh>,
+    pub outline_range: Range<usize>,
 }
 
-fn create_sphere(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-) {
-    let sphere = subsphere::HexSphere::from_kis(
-        subsphere::icosphere()
-            .subdivide_edge(NonZero::new(60).unwrap())
-            .with_projector(subsphere::proj::Fuller),
-    )
-    .unwrap();
+/// One of the merged meshes the globe is drawn with
+#[derive(Component)]
+pub struct GlobeChunk;
 
+fn create_sphere(mut commands: Commands, config: Res<WorldGenConfig>) {
+    let icosphere =
+        subsphere::icosphere().subdivide_edge(NonZero::new(config.subdivisions).unwrap());
+
+    // the projector is a type parameter in subsphere, so pick the concrete sphere here
+    match config.projector {
+        Projector::Fuller => spawn_faces(
+            &subsphere::HexSphere::from_kis(icosphere.with_projector(subsphere::proj::Fuller))
+                .unwrap(),
+            &mut commands,
+        ),
+        Projector::Gnomonic => spawn_faces(
+            &subsphere::HexSphere::from_kis(icosphere.with_projector(subsphere::proj::Gnomonic))
+                .unwrap(),
+            &mut commands,
+        ),
+    }
+}
+
+fn spawn_faces<Proj: Eq + Copy + BaseTriProjector>(
+    sphere: &subsphere::HexSphere<Proj>,
+    commands: &mut Commands,
+) {
     let mut face_entities = Vec::new();
 
     // First pass: create entities and store them
@@ -44,58 +129,149 @@ fn create_sphere(
     }
 
     // Second pass: populate entities
-    for (i, face) in sphere.faces().enumerate() {
-        let centre_pos = get_centre_vec(face);
+    for face in sphere.faces() {
+        let mut neighbours = Vec::new();
+        for side in face.sides() {
+            let neighbour_index = side.twin().inside().index();
+            neighbours.push(face_entities[neighbour_index]);
+        }
+        commands.entity(face_entities[face.index()]).insert((
+            Face {
+                index: face.index(),
+                centre_pos: get_centre_vec(face),
+                vertices: get_vertex_vecs(face),
+            },
+            FaceNeighbours(neighbours),
+        ));
+    }
+}
 
-        let positions = build_fan_triangulation(face);
+/// Build the merged globe meshes, one per chunk of faces in subsphere order
+fn create_globe_meshes(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    query_faces: Query<(Entity, &Face)>,
+) {
+    let mut faces: Vec<(Entity, &Face)> = query_faces.iter().collect();
+    faces.sort_unstable_by_key(|(_, face)| face.index);
+
+    // every chunk shares these, face colours come from the vertex colours
+    let globe_material = materials.add(StandardMaterial {
+        base_color: Color::WHITE,
+        ..default()
+    });
+    let outline_material = materials.add(StandardMaterial {
+        base_color: Color::BLACK,
+        // unlit: true,
+        ..default()
+    });
+
+    for chunk in faces.chunks(FACES_PER_CHUNK) {
+        let mut positions = Vec::new();
+        let mut face_ranges = Vec::new();
+        let mut line_vertices = Vec::new();
+        let mut outline_ranges = Vec::new();
+
+        for (_, face) in chunk {
+            let start = positions.len();
+            positions.extend(build_face_positions(&face.vertices, 1.0, &[]));
+            face_ranges.push(start..positions.len());
+
+            let start = line_vertices.len();
+            line_vertices.extend(build_outline(&face.vertices, 1.0));
+            outline_ranges.push(start..line_vertices.len());
+        }
+
+        let colours = vec![LinearRgba::WHITE.to_f32_array(); positions.len()];
 
         let mut mesh = Mesh::new(
             PrimitiveTopology::TriangleList,
             RenderAssetUsages::default(),
         );
         mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colours);
         mesh.compute_flat_normals();
 
-        let mut neighbours = Vec::new();
-        for side in face.sides() {
-            let neighbour_index = side.twin().inside().index();
-            neighbours.push(face_entities[neighbour_index]);
-        }
-        commands.entity(face_entities[i]).insert((
-            Mesh3d(meshes.add(mesh)),
-            MeshMaterial3d(materials.add(StandardMaterial {
-                base_color: Color::srgb(1.0, 1.0, 1.0),
-                ..default()
-            })),
-            Face { centre_pos },
-            FaceNeighbours(neighbours),
+        let mesh_handle = meshes.add(mesh);
+
+        commands.spawn((
+            Mesh3d(mesh_handle.clone()),
+            MeshMaterial3d(globe_material.clone()),
             Transform::from_xyz(0.0, 0.0, 0.0),
+            GlobeChunk,
         ));
+        let mut outline_mesh = Mesh::new(PrimitiveTopology::LineList, RenderAssetUsages::default());
+        outline_mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, line_vertices);
 
-        let outline_mesh = build_outline_mesh(face);
-
-        commands.entity(face_entities[i]).with_children(|c| {
-            c.spawn((
-                Mesh3d(meshes.add(outline_mesh)),
-                MeshMaterial3d(materials.add(StandardMaterial {
-                    base_color: Color::BLACK,
-                    // unlit: true,
-                    ..default()
-                })),
-            ));
-        });
+        let outline_handle = meshes.add(outline_mesh);
+
+        commands.spawn((
+            Mesh3d(outline_handle.clone()),
+            MeshMaterial3d(outline_material.clone()),
+            Transform::from_xyz(0.0, 0.0, 0.0),
+            // lines can't be hit by a ray anyway, no point testing them
+            Pickable::IGNORE,
+            GlobeChunk,
+        ));
+
+        for (((entity_id, _), range), outline_range) in
+            chunk.iter().zip(face_ranges).zip(outline_ranges)
+        {
+            commands.entity(*entity_id).insert(FaceVertices {
+                mesh: mesh_handle.clone(),
+                range,
+                outline: outline_handle.clone(),
+                outline_range,
+            });
+        }
+    }
+}
+
+/// Throw the globe away and build it again from the current config and seed, e.g. after loading a save
+pub fn rebuild_globe(world: &mut World) {
+    let old_entities: Vec<Entity> = world
+        .query_filtered::<Entity, Or<(With<Face>, With<GlobeChunk>)>>()
+        .iter(world)
+        .collect();
+    for entity_id in old_entities {
+        world.despawn(entity_id);
+    }
+
+    // start every stream over so the new seed gives the same world it would from scratch
+    let world_rng = WorldRng::from_world(world);
+    world.insert_resource(world_rng);
+
+    world
+        .run_system_cached(create_sphere)
+        .expect("create_sphere can always run");
+    world
+        .run_system_cached(create_palette)
+        .expect("create_palette can always run");
+    // headless apps have nothing to draw with
+    if world.contains_resource::<Assets<Mesh>>() {
+        world
+            .run_system_cached(create_globe_meshes)
+            .expect("create_globe_meshes can run once meshes exist");
     }
 }
 
 // Create plates colour palette
-fn create_palette(mut commands: Commands) {
-    let mut rng = rand::rng();
-    let colour_palette = gen_colour_palette(N_PLATES, &mut rng);
-    commands.insert_resource(PlatePalette(colour_palette));
+fn create_palette(
+    mut commands: Commands,
+    mut world_rng: ResMut<WorldRng>,
+    config: Res<WorldGenConfig>,
+) {
+    let rng = world_rng.stream(RngStream::Palette);
+    let colour_palette = gen_colour_palette(config.n_plates, rng);
+    commands.insert_resource(PlatePalette {
+        colours: colour_palette,
+        order: Vec::new(),
+    });
 }
 
 // TODO: merge this into create_palette?
-fn gen_colour_palette(n: usize, rng: &mut ThreadRng) -> Vec<Color> {
+fn gen_colour_palette(n: usize, rng: &mut impl Rng) -> Vec<Color> {
     (0..n)
         .map(|_| {
             Color::srgb(
@@ -107,7 +283,7 @@ fn gen_colour_palette(n: usize, rng: &mut ThreadRng) -> Vec<Color> {
         .collect()
 }
 
-fn get_centre_vec(face: subsphere::hex::Face<subsphere::proj::Fuller>) -> Vec3 {
+fn get_centre_vec<Proj: Eq + Copy + BaseTriProjector>(face: subsphere::hex::Face<Proj>) -> Vec3 {
     let centre_position_array = face.center().pos();
     Vec3::new(
         centre_position_array[0] as f32,
@@ -116,9 +292,99 @@ fn get_centre_vec(face: subsphere::hex::Face<subsphere::proj::Fuller>) -> Vec3 {
     )
 }
 
-fn build_fan_triangulation(face: subsphere::hex::Face<subsphere::proj::Fuller>) -> Vec<[f32; 3]> {
-    let face_vertices: Vec<_> = face.vertices().map(|v| v.pos()).collect();
+fn get_vertex_vecs<Proj: Eq + Copy + BaseTriProjector>(
+    face: subsphere::hex::Face<Proj>,
+) -> Vec<Vec3> {
+    face.vertices()
+        .map(|v| {
+            let p = v.pos();
+            Vec3::new(p[0] as f32, p[1] as f32, p[2] as f32)
+        })
+        .collect()
+}
+
+/// Walk across the faces towards `point` until no neighbour is closer,
+/// the face whose centre is nearest is the one the point falls in
+pub fn nearest_face(
+    point: Vec3,
+    start: usize,
+    centres: &[Vec3],
+    neighbours: &[Vec<usize>],
+) -> usize {
+    let mut current = start;
+    let mut best = centres[current].dot(point);
+    loop {
+        let next = neighbours[current]
+            .iter()
+            .map(|&i| (i, centres[i].dot(point)))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        match next {
+            Some((i, closeness)) if closeness > best => {
+                current = i;
+                best = closeness;
+            }
+            _ => return current,
+        }
+    }
+}
 
+/// Radius of the face across each side of `face`, side `j` running from corner `j` to the next,
+/// from its neighbours and their radii. Sides with no neighbour across get `radius`
+pub fn side_floors<'a>(
+    face: &Face,
+    radius: f32,
+    neighbours: impl IntoIterator<Item = (&'a Face, f32)>,
+) -> Vec<f32> {
+    let corners = &face.vertices;
+    let same = |a: Vec3, b: Vec3| a.distance_squared(b) < 1e-10;
+    let mut floors = vec![radius; corners.len()];
+    for (neighbour, neighbour_radius) in neighbours {
+        let Some((p, q)) = face.shared_side(neighbour) else {
+            continue;
+        };
+        let side = (0..corners.len()).find(|&j| {
+            let (a, b) = (corners[j], corners[(j + 1) % corners.len()]);
+            (same(a, p) && same(b, q)) || (same(a, q) && same(b, p))
+        });
+        if let Some(j) = side {
+            floors[j] = neighbour_radius;
+        }
+    }
+    floors
+}
+
+/// Triangles for the top of a face at `radius`, plus a wall down each side to the face across it
+/// from `side_floors` where that one is lower, so neighbours at different heights don't leave
+/// gaps between them. Sides missing from `side_floors` get no wall
+pub fn build_face_positions(
+    face_vertices: &[Vec3],
+    radius: f32,
+    side_floors: &[f32],
+) -> Vec<[f32; 3]> {
+    let top: Vec<Vec3> = face_vertices.iter().map(|v| *v * radius).collect();
+    let mut positions = build_fan_triangulation(&top);
+
+    for j in 0..face_vertices.len() {
+        // the higher of two faces puts the wall up between them
+        let Some(&floor) = side_floors.get(j).filter(|floor| **floor < radius) else {
+            continue;
+        };
+        let a = face_vertices[j];
+        let b = face_vertices[(j + 1) % face_vertices.len()];
+
+        // wound so the wall faces away from the face's centre
+        positions.push((a * radius).to_array());
+        positions.push((a * floor).to_array());
+        positions.push((b * floor).to_array());
+
+        positions.push((a * radius).to_array());
+        positions.push((b * floor).to_array());
+        positions.push((b * radius).to_array());
+    }
+    positions
+}
+
+pub fn build_fan_triangulation(face_vertices: &[Vec3]) -> Vec<[f32; 3]> {
     let mut positions = Vec::new();
     let v0 = face_vertices[0];
 
@@ -126,50 +392,41 @@ fn build_fan_triangulation(face: subsphere::hex::Face<subsphere::proj::Fuller>)
         let v1 = face_vertices[j];
         let v2 = face_vertices[j + 1];
 
-        positions.push([v0[0] as f32, v0[1] as f32, v0[2] as f32]);
-        positions.push([v1[0] as f32, v1[1] as f32, v1[2] as f32]);
-        positions.push([v2[0] as f32, v2[1] as f32, v2[2] as f32]);
+        positions.push(v0.to_array());
+        positions.push(v1.to_array());
+        positions.push(v2.to_array());
     }
     positions
 }
 
-fn build_outline_mesh(face: subsphere::hex::Face<subsphere::proj::Fuller>) -> Mesh {
-    let mut face_vertices: Vec<Vec3> = face
-        .vertices()
-        .map(|v| {
-            let p = v.pos();
-            Vec3::new(p[0] as f32, p[1] as f32, p[2] as f32) * 1.0001 // tiny little offset so it sits just above the sphere
-        })
-        .collect();
-
-    // to wrap back to the first point
-    face_vertices.push(face_vertices[0]);
+/// Line segments around the edge of a face sitting at `radius`
+pub fn build_outline(face_vertices: &[Vec3], radius: f32) -> Vec<[f32; 3]> {
+    // tiny little offset so it sits just above the face
+    let face_vertices: Vec<Vec3> = face_vertices.iter().map(|v| *v * radius * 1.0001).collect();
 
-    let mut mesh = Mesh::new(PrimitiveTopology::LineStrip, RenderAssetUsages::default());
-
-    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, face_vertices);
-
-    mesh
-}
-
-fn change_face_color(
-    mut commands: Commands,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    query: Query<(Entity, &MeshMaterial3d<StandardMaterial>, &ChangeColour), With<Face>>,
-) {
-    for (entity_id, material_handle, colour) in query.iter() {
-        if let Some(material) = materials.get_mut(material_handle) {
-            material.base_color = colour.colour;
-        }
-        commands.entity(entity_id).remove::<ChangeColour>();
+    // one segment per side, wrapping back to the first point
+    let mut line_vertices = Vec::new();
+    for j in 0..face_vertices.len() {
+        line_vertices.push(face_vertices[j].to_array());
+        line_vertices.push(face_vertices[(j + 1) % face_vertices.len()].to_array());
     }
+    line_vertices
 }
 
+/// Builds the face entities and plate palette, everything world generation needs
 pub struct SetupPlugin;
 
 impl Plugin for SetupPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, (create_sphere, create_palette))
-            .add_systems(Update, change_face_color);
+        app.add_systems(Startup, (create_sphere, create_palette));
+    }
+}
+
+/// Draws the globe, only needed when there is a window to draw it in
+pub struct GlobeRenderPlugin;
+
+impl Plugin for GlobeRenderPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostStartup, create_globe_meshes);
     }
 }
@@ -0,0 +1,816 @@
+// move the plates over time: each plate turns about its euler pole, and once it has turned far
+// enough its crust is carried across the fixed grid of faces, making new ocean floor where plates
+// pull apart and pushing crust under where they meet
+
+use std::collections::VecDeque;
+
+use bevy::ecs::query::QueryData;
+use bevy::ecs::system::SystemParam;
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::crust::{CrustThickness, CrustType};
+use crate::elevation::{
+    BoundaryCrust, Elevation, base_elevation, boundary_effect, strongest_effect,
+};
+use crate::setup::{Face, FaceNeighbours, nearest_face};
+use crate::states::{GameState, SimulationState};
+use crate::ui::cleanup_ui;
+use crate::worldgen::{
+    FacePlateVelocity, Plate, PlateBoundary, classify_boundary, fit_plate_rotations,
+};
+
+/// Millions of years that pass each fixed tick at normal speed
+const MYR_PER_TICK: f32 = 1.0;
+/// Multiples of normal speed the clock can run at, faster moves crust in bigger, coarser jumps
+const SPEEDS: [f32; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+const NORMAL_SPEED: usize = 2;
+/// How fast a plate with a rotation vector of length 1 turns, in radians per million years (about 1°)
+const RADIANS_PER_MYR: f32 = 0.0175;
+/// Height of fresh ocean floor at a spreading ridge, in km
+const RIDGE_ELEVATION: f32 = -2.5;
+/// Height continental crust gains when another continent is pushed into it, in km
+const COLLISION_UPLIFT: f32 = 0.3;
+/// Millions of years it takes crust to settle most of the way to the height its boundary pushes it to
+const SETTLING_MYR: f32 = 50.0;
+/// Fraction of the difference from its neighbours that a face wears away each million years
+const SMOOTHING_PER_MYR: f32 = 0.01;
+/// Elevation changes smaller than this (in km) aren't written, so settled faces aren't re-meshed every tick
+const MIN_ELEVATION_CHANGE: f32 = 0.001;
+
+/// Geological time in the simulation, how fast it passes and any single steps waiting to run
+#[derive(Resource)]
+pub struct SimulationClock {
+    /// Millions of years since the simulation started
+    pub elapsed_myr: f32,
+    /// Index into `SPEEDS`
+    speed: usize,
+    /// Ticks to run while paused, one per press of step
+    steps: u32,
+}
+
+impl Default for SimulationClock {
+    fn default() -> Self {
+        Self {
+            elapsed_myr: 0.0,
+            speed: NORMAL_SPEED,
+            steps: 0,
+        }
+    }
+}
+
+impl SimulationClock {
+    /// Millions of years each tick covers at the current speed, everything that changes per tick
+    /// is scaled by this
+    pub fn tick_myr(&self) -> f32 {
+        MYR_PER_TICK * SPEEDS[self.speed]
+    }
+
+    pub fn speed(&self) -> f32 {
+        SPEEDS[self.speed]
+    }
+}
+
+/// How each plate turns, and how far it has turned since its crust last moved
+#[derive(Resource, Default)]
+struct PlateKinematics(HashMap<usize, PlateMotion>);
+
+struct PlateMotion {
+    /// Axis scaled by angular speed, in the same units as `FacePlateVelocity`
+    rotation: Vec3,
+    /// Radians turned since the crust was last carried along
+    turned: f32,
+}
+
+impl PlateKinematics {
+    /// Turn every plate by `myr` million years' worth, returning the rotation of each one that has now
+    /// turned at least `spacing` radians since its crust last moved
+    fn turn(&mut self, myr: f32, spacing: f32) -> Vec<(usize, Quat)> {
+        let mut moves = Vec::new();
+        for (&plate, motion) in &mut self.0 {
+            motion.turned += motion.rotation.length() * RADIANS_PER_MYR * myr;
+            if motion.turned >= spacing {
+                moves.push((
+                    plate,
+                    Quat::from_axis_angle(motion.rotation.normalize(), motion.turned),
+                ));
+                motion.turned = 0.0;
+            }
+        }
+        moves.sort_unstable_by_key(|(plate, _)| *plate);
+        moves
+    }
+
+    fn velocity(&self, plate: usize, position: Vec3) -> Vec3 {
+        self.0
+            .get(&plate)
+            .map_or(Vec3::ZERO, |motion| motion.rotation.cross(position))
+    }
+}
+
+/// A face's worth of crust, what moves when a plate does
+#[derive(Clone, Copy)]
+struct Crust {
+    plate: usize,
+    kind: CrustType,
+    thickness: CrustThickness,
+    elevation: f32,
+}
+
+impl Crust {
+    fn continental(self) -> bool {
+        self.kind == CrustType::Continental
+    }
+}
+
+#[derive(QueryData)]
+struct SimulatedFace {
+    entity: Entity,
+    face: &'static Face,
+    neighbours: &'static FaceNeighbours,
+    plate: &'static Plate,
+    crust: &'static CrustType,
+    thickness: &'static CrustThickness,
+    elevation: &'static Elevation,
+    boundary: Option<&'static PlateBoundary>,
+}
+
+/// Work the plate rotations out again whenever velocities arrive from outside the simulation,
+/// when it first starts and when a save is loaded
+fn fit_kinematics(
+    mut kinematics: ResMut<PlateKinematics>,
+    query_faces: Query<(&Face, &Plate, &FacePlateVelocity)>,
+    added_velocities: Query<(), Added<FacePlateVelocity>>,
+) {
+    if added_velocities.is_empty() {
+        return;
+    }
+
+    let rotations = fit_plate_rotations(
+        query_faces
+            .iter()
+            .map(|(face, plate, velocity)| (face.centre_pos, plate.0, velocity.velocity)),
+    );
+    // how far each plate has turned carries over, a loaded save only brings that part back
+    let turned = |plate| kinematics.0.get(&plate).map_or(0.0, |motion| motion.turned);
+    let motions = rotations
+        .into_iter()
+        .map(|(plate, rotation)| {
+            (
+                plate,
+                PlateMotion {
+                    rotation,
+                    turned: turned(plate),
+                },
+            )
+        })
+        .collect();
+    kinematics.0 = motions;
+}
+
+/// One tick of plate tectonics: turn the plates, carry crust along with any that have turned far
+/// enough, then redraw the boundaries and let the crust settle towards what they push it to
+fn step_plates(
+    mut commands: Commands,
+    clock: Res<SimulationClock>,
+    mut kinematics: ResMut<PlateKinematics>,
+    query_faces: Query<SimulatedFace>,
+) {
+    // flatten the faces into plain arrays in subsphere order, so the step is the same
+    // however the ECS lays them out
+    let mut faces: Vec<_> = query_faces.iter().collect();
+    faces.sort_unstable_by_key(|face| face.face.index);
+    if faces.is_empty() {
+        return;
+    }
+    let slots: HashMap<Entity, usize> = faces
+        .iter()
+        .enumerate()
+        .map(|(i, face)| (face.entity, i))
+        .collect();
+    let centres: Vec<Vec3> = faces
+        .iter()
+        .map(|face| face.face.centre_pos.normalize())
+        .collect();
+    let neighbours: Vec<Vec<usize>> = faces
+        .iter()
+        .map(|face| {
+            face.neighbours
+                .iter()
+                .filter_map(|entity_id| slots.get(entity_id).copied())
+                .collect()
+        })
+        .collect();
+    let before: Vec<Crust> = faces
+        .iter()
+        .map(|face| Crust {
+            plate: face.plate.0,
+            kind: *face.crust,
+            thickness: *face.thickness,
+            elevation: face.elevation.0,
+        })
+        .collect();
+
+    // crust only moves once its plate has turned about as far as the gap between two faces
+    #[allow(clippy::cast_precision_loss)]
+    let spacing = (4.0 * std::f32::consts::PI / faces.len() as f32).sqrt();
+    let moves = kinematics.turn(clock.tick_myr(), spacing);
+
+    let after = if moves.is_empty() {
+        before.clone()
+    } else {
+        carry_crust(&before, &moves, &centres, &neighbours)
+    };
+
+    let velocities: Vec<Vec3> = (0..faces.len())
+        .map(|i| kinematics.velocity(after[i].plate, centres[i]))
+        .collect();
+    let boundaries: Vec<Option<PlateBoundary>> = (0..faces.len())
+        .map(|i| {
+            let mut boundary = PlateBoundary::between(
+                faces[i].face,
+                after[i].plate,
+                neighbours[i]
+                    .iter()
+                    .map(|&j| (faces[j].entity, faces[j].face, after[j].plate)),
+            )?;
+            for side in &mut boundary.sides {
+                let j = slots[&side.across];
+                side.class = Some(classify_boundary(
+                    centres[i],
+                    velocities[i],
+                    centres[j],
+                    velocities[j],
+                ));
+            }
+            Some(boundary)
+        })
+        .collect();
+
+    for (i, face) in faces.iter().enumerate() {
+        let crust = after[i];
+        let uplift = boundaries[i].as_ref().map_or(0.0, |boundary| {
+            boundary_push(i, &after, &velocities, boundary, &slots)
+        });
+        let elevation = settled_elevation(i, &after, uplift, &neighbours, clock.tick_myr());
+
+        let mut entity = commands.entity(face.entity);
+        if crust.plate != before[i].plate {
+            entity.insert((
+                Plate(crust.plate),
+                FacePlateVelocity {
+                    velocity: kinematics.velocity(crust.plate, face.face.centre_pos),
+                },
+            ));
+        }
+        if crust.kind != before[i].kind {
+            entity.insert(crust.kind);
+        }
+        if crust.thickness != before[i].thickness {
+            entity.insert(crust.thickness);
+        }
+        if (elevation - before[i].elevation).abs() >= MIN_ELEVATION_CHANGE {
+            entity.insert(Elevation(elevation));
+        }
+        match (&boundaries[i], face.boundary) {
+            (Some(boundary), old) if old != Some(boundary) => {
+                entity.insert(boundary.clone());
+            }
+            (None, Some(_)) => {
+                entity.remove::<PlateBoundary>();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// How far the classified sides of face `i`'s boundary push it up or down
+fn boundary_push(
+    i: usize,
+    crust: &[Crust],
+    velocities: &[Vec3],
+    boundary: &PlateBoundary,
+    slots: &HashMap<Entity, usize>,
+) -> f32 {
+    let boundary_crust = |i: usize| BoundaryCrust {
+        continental: crust[i].continental(),
+        thickness: crust[i].thickness.0,
+        speed: velocities[i].length(),
+    };
+    let effects = boundary.sides.iter().filter_map(|side| {
+        let across = *slots.get(&side.across)?;
+        Some(boundary_effect(
+            boundary_crust(i),
+            boundary_crust(across),
+            side.class?,
+        ))
+    });
+    strongest_effect(effects).map_or(0.0, |(uplift, _)| uplift)
+}
+
+/// Where a face's height is headed after `myr` million years: boundaries push the crust up or down by `uplift`, everywhere
+/// else it sinks back to its resting height, and every face wears down towards its neighbours
+fn settled_elevation(
+    i: usize,
+    crust: &[Crust],
+    uplift: f32,
+    neighbours: &[Vec<usize>],
+    myr: f32,
+) -> f32 {
+    let Crust {
+        kind,
+        thickness,
+        elevation,
+        ..
+    } = crust[i];
+
+    let target = base_elevation(kind, thickness) + uplift;
+    #[allow(clippy::cast_precision_loss)]
+    let neighbour_mean = neighbours[i]
+        .iter()
+        .map(|&j| crust[j].elevation)
+        .sum::<f32>()
+        / neighbours[i].len().max(1) as f32;
+
+    let settle = 1.0 - (-myr / SETTLING_MYR).exp();
+    let smooth = 1.0 - (1.0 - SMOOTHING_PER_MYR).powf(myr);
+    elevation + (target - elevation) * settle + (neighbour_mean - elevation) * smooth
+}
+
+/// Carry the crust of each plate in `moves` along by its rotation.
+/// Each face looks back along every plate that could have reached it, rather than each face
+/// being pushed forward, so a plate moving across the grid doesn't tear holes in itself.
+fn carry_crust(
+    before: &[Crust],
+    moves: &[(usize, Quat)],
+    centres: &[Vec3],
+    neighbours: &[Vec<usize>],
+) -> Vec<Crust> {
+    let moving: HashMap<usize, Quat> = moves.iter().copied().collect();
+
+    // crust on plates that aren't moving this tick stays where it is
+    let mut after: Vec<Option<Crust>> = before
+        .iter()
+        .map(|crust| (!moving.contains_key(&crust.plate)).then_some(*crust))
+        .collect();
+
+    for &(plate, rotation) in moves {
+        // where the plate's faces land, and the faces around them to catch any gaps between
+        let mut reached: Vec<usize> = Vec::new();
+        for (i, crust) in before.iter().enumerate() {
+            if crust.plate != plate {
+                continue;
+            }
+            let landed = nearest_face(rotation * centres[i], i, centres, neighbours);
+            reached.push(landed);
+            reached.extend(&neighbours[landed]);
+        }
+        reached.sort_unstable();
+        reached.dedup();
+
+        let back = rotation.inverse();
+        for target in reached {
+            let source = nearest_face(back * centres[target], target, centres, neighbours);
+            if before[source].plate != plate {
+                continue;
+            }
+            let arriving = before[source];
+            let Some(existing) = after[target] else {
+                after[target] = Some(arriving);
+                continue;
+            };
+
+            let (upper, stuck) = collide(existing, arriving);
+            after[target] = Some(upper);
+            if let Some(stuck) = stuck {
+                leave_behind(stuck, source, target, &mut after, neighbours);
+            }
+        }
+    }
+
+    // wherever a plate pulled away from and nothing took its place is new ocean floor on that plate
+    before
+        .iter()
+        .zip(after)
+        .map(|(old, new)| {
+            new.unwrap_or(Crust {
+                plate: old.plate,
+                kind: CrustType::Oceanic,
+                thickness: CrustThickness::OCEANIC,
+                elevation: RIDGE_ELEVATION,
+            })
+        })
+        .collect()
+}
+
+/// Continent that couldn't get through a collision is left behind, in the nearest face to
+/// `source` that nothing has moved into yet. With no face free anywhere it piles up on top of
+/// the continent at `target` instead, so continent is never lost.
+fn leave_behind(
+    stuck: Crust,
+    source: usize,
+    target: usize,
+    after: &mut [Option<Crust>],
+    neighbours: &[Vec<usize>],
+) {
+    let mut seen = vec![false; after.len()];
+    seen[source] = true;
+    let mut queue = VecDeque::from([source]);
+    while let Some(i) = queue.pop_front() {
+        if after[i].is_none() {
+            after[i] = Some(stuck);
+            return;
+        }
+        for &n in &neighbours[i] {
+            if !seen[n] {
+                seen[n] = true;
+                queue.push_back(n);
+            }
+        }
+    }
+
+    if let Some(upper) = &mut after[target] {
+        upper.thickness.0 += stuck.thickness.0;
+    }
+}
+
+/// Two pieces of crust ended up on the same face, whichever floats better stays on top and the
+/// ocean floor under it is lost. Continent is too light to sink, so when two continents meet the
+/// one already there holds its ground and the arriving one is handed back to be left behind.
+fn collide(existing: Crust, arriving: Crust) -> (Crust, Option<Crust>) {
+    match (existing.continental(), arriving.continental()) {
+        // the continent in the way crumples up into mountains
+        (true, true) => (
+            Crust {
+                elevation: existing.elevation.max(arriving.elevation) + COLLISION_UPLIFT,
+                ..existing
+            },
+            Some(arriving),
+        ),
+        // ocean floor is denser than continent, it always goes under
+        (true, false) => (existing, None),
+        (false, true) => (arriving, None),
+        // between two oceans the lower numbered plate goes under, the same rule elevation uses,
+        // so a boundary sinks the same way along its whole length
+        (false, false) => {
+            if arriving.plate < existing.plate {
+                (existing, None)
+            } else {
+                (arriving, None)
+            }
+        }
+    }
+}
+
+/// Tick while running, or once for each step asked for while paused
+fn should_tick(state: Option<Res<State<SimulationState>>>, clock: Res<SimulationClock>) -> bool {
+    match state.as_deref().map(State::get) {
+        Some(SimulationState::Running) => true,
+        Some(SimulationState::Paused) => clock.steps > 0,
+        None => false,
+    }
+}
+
+fn advance_clock(mut clock: ResMut<SimulationClock>) {
+    clock.elapsed_myr += clock.tick_myr();
+    clock.steps = clock.steps.saturating_sub(1);
+}
+
+/// Every simulation starts from the beginning of time, however the last one left the clock.
+/// Done on the way out rather than in, so a simulation loaded from a save keeps its place
+fn reset_clock(mut clock: ResMut<SimulationClock>, mut kinematics: ResMut<PlateKinematics>) {
+    *clock = SimulationClock::default();
+    for motion in kinematics.0.values_mut() {
+        motion.turned = 0.0;
+    }
+}
+
+/// Put the clock and plate motions back as a save had them, in apps that simulate
+pub fn restore_simulation(world: &mut World, saved: &SavedSimulation) {
+    if let Some(mut clock) = world.get_resource_mut::<SimulationClock>() {
+        *clock = SimulationClock {
+            elapsed_myr: saved.elapsed_myr,
+            speed: saved.speed.min(SPEEDS.len() - 1),
+            steps: 0,
+        };
+    }
+    // the rotations come back from the loaded velocities, only how far each has turned is kept
+    if let Some(mut kinematics) = world.get_resource_mut::<PlateKinematics>() {
+        kinematics.0 = saved
+            .turned
+            .iter()
+            .map(|&(plate, turned)| {
+                let motion = PlateMotion {
+                    rotation: Vec3::ZERO,
+                    turned,
+                };
+                (plate, motion)
+            })
+            .collect();
+    }
+}
+
+/// What a save keeps of a simulation, so loading it carries on rather than starting over
+#[derive(Serialize, Deserialize)]
+pub struct SavedSimulation {
+    elapsed_myr: f32,
+    /// Index into `SPEEDS`
+    speed: usize,
+    /// Radians each plate has turned since its crust last moved
+    turned: Vec<(usize, f32)>,
+}
+
+/// The simulation's clock and plate motions, for saving, missing when there's no simulation
+#[derive(SystemParam)]
+pub struct SimulationProgress<'w> {
+    clock: Option<Res<'w, SimulationClock>>,
+    kinematics: Option<Res<'w, PlateKinematics>>,
+}
+
+impl SimulationProgress<'_> {
+    pub fn saved(&self) -> SavedSimulation {
+        let mut turned: Vec<(usize, f32)> = self
+            .kinematics
+            .iter()
+            .flat_map(|kinematics| &kinematics.0)
+            .map(|(plate, motion)| (*plate, motion.turned))
+            .collect();
+        turned.sort_unstable_by_key(|(plate, _)| *plate);
+        SavedSimulation {
+            elapsed_myr: self.clock.as_ref().map_or(0.0, |clock| clock.elapsed_myr),
+            speed: self
+                .clock
+                .as_ref()
+                .map_or(NORMAL_SPEED, |clock| clock.speed),
+            turned,
+        }
+    }
+}
+
+/// Drift the plates while the simulation is running
+pub struct SimulationPlugin;
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PlateKinematics>()
+            .init_resource::<SimulationClock>()
+            .add_systems(OnExit(GameState::Simulation), reset_clock)
+            .add_systems(
+                FixedUpdate,
+                (advance_clock, fit_kinematics, step_plates)
+                    .chain()
+                    .run_if(should_tick),
+            );
+    }
+}
+
+#[derive(Component, Clone, Copy)]
+enum TimeControl {
+    PlayPause,
+    Step,
+    Slower,
+    Faster,
+}
+
+impl TimeControl {
+    const ALL: [TimeControl; 4] = [
+        TimeControl::PlayPause,
+        TimeControl::Step,
+        TimeControl::Slower,
+        TimeControl::Faster,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            TimeControl::PlayPause => "Pause / play (Space)",
+            TimeControl::Step => "Step (N)",
+            TimeControl::Slower => "Slower (-)",
+            TimeControl::Faster => "Faster (=)",
+        }
+    }
+
+    fn key(self) -> KeyCode {
+        match self {
+            TimeControl::PlayPause => KeyCode::Space,
+            TimeControl::Step => KeyCode::KeyN,
+            TimeControl::Slower => KeyCode::Minus,
+            TimeControl::Faster => KeyCode::Equal,
+        }
+    }
+
+    fn apply(
+        self,
+        state: &SimulationState,
+        next_state: &mut NextState<SimulationState>,
+        clock: &mut SimulationClock,
+    ) {
+        match self {
+            TimeControl::PlayPause => next_state.set(match state {
+                SimulationState::Running => SimulationState::Paused,
+                SimulationState::Paused => SimulationState::Running,
+            }),
+            // stepping pauses first, so a step while running stops on the very next tick
+            TimeControl::Step => {
+                if *state == SimulationState::Running {
+                    next_state.set(SimulationState::Paused);
+                } else {
+                    clock.steps += 1;
+                }
+            }
+            TimeControl::Slower => clock.speed = clock.speed.saturating_sub(1),
+            TimeControl::Faster => clock.speed = (clock.speed + 1).min(SPEEDS.len() - 1),
+        }
+    }
+}
+
+#[derive(Component)]
+struct TimeControlsUi;
+
+#[derive(Component)]
+struct SimulationClockUiText;
+
+const BUTTON_COLOUR: Color = Color::srgba(0.1, 0.1, 0.1, 0.8);
+
+fn setup_time_controls_ui(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(5.0),
+                width: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(4.0),
+                ..default()
+            },
+            Pickable::IGNORE,
+            TimeControlsUi,
+        ))
+        .with_children(|parent| {
+            parent.spawn((Text::new(""), SimulationClockUiText));
+            parent
+                .spawn(Node {
+                    column_gap: Val::Px(4.0),
+                    ..default()
+                })
+                .with_children(|row| {
+                    for control in TimeControl::ALL {
+                        row.spawn((
+                            Button,
+                            Node {
+                                padding: UiRect::axes(Val::Px(8.0), Val::Px(4.0)),
+                                ..default()
+                            },
+                            BackgroundColor(BUTTON_COLOUR),
+                            control,
+                        ))
+                        .with_child(Text::new(control.label()));
+                    }
+                });
+        });
+}
+
+fn handle_time_controls(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    q_buttons: Query<(&Interaction, &TimeControl), Changed<Interaction>>,
+    state: Res<State<SimulationState>>,
+    mut next_state: ResMut<NextState<SimulationState>>,
+    mut clock: ResMut<SimulationClock>,
+) {
+    let pressed_keys = TimeControl::ALL
+        .into_iter()
+        .filter(|control| keyboard_input.just_pressed(control.key()));
+    let pressed_buttons = q_buttons
+        .iter()
+        .filter(|(interaction, _)| **interaction == Interaction::Pressed)
+        .map(|(_, control)| *control);
+
+    for control in pressed_keys.chain(pressed_buttons) {
+        control.apply(state.get(), &mut next_state, &mut clock);
+    }
+}
+
+fn update_clock_ui(
+    clock: Res<SimulationClock>,
+    state: Res<State<SimulationState>>,
+    mut q_text: Query<&mut Text, With<SimulationClockUiText>>,
+) {
+    for mut text in &mut q_text {
+        text.0 = format!(
+            "{:.1} million years, {}x speed{}",
+            clock.elapsed_myr,
+            clock.speed(),
+            match state.get() {
+                SimulationState::Running => "",
+                SimulationState::Paused => ", paused",
+            }
+        );
+    }
+}
+
+/// Buttons and keys for pausing, stepping and speeding up the simulation
+pub struct SimulationControlsPlugin;
+
+impl Plugin for SimulationControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Simulation), setup_time_controls_ui)
+            .add_systems(OnExit(GameState::Simulation), cleanup_ui::<TimeControlsUi>)
+            .add_systems(
+                Update,
+                (
+                    handle_time_controls,
+                    update_clock_ui.run_if(
+                        resource_changed::<SimulationClock>.or(state_changed::<SimulationState>),
+                    ),
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Simulation)),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn continent(plate: usize) -> Crust {
+        Crust {
+            plate,
+            kind: CrustType::Continental,
+            thickness: CrustThickness::MARGIN,
+            elevation: 0.5,
+        }
+    }
+
+    fn ocean(plate: usize) -> Crust {
+        Crust {
+            plate,
+            kind: CrustType::Oceanic,
+            thickness: CrustThickness::OCEANIC,
+            elevation: -4.0,
+        }
+    }
+
+    /// `count` faces spaced evenly around the equator, each next to the one either side
+    fn ring(count: u8) -> (Vec<Vec3>, Vec<Vec<usize>>) {
+        let centres = (0..count)
+            .map(|i| {
+                let angle = std::f32::consts::TAU * f32::from(i) / f32::from(count);
+                Vec3::new(angle.cos(), angle.sin(), 0.0)
+            })
+            .collect();
+        let count = usize::from(count);
+        let neighbours = (0..count)
+            .map(|i| vec![(i + count - 1) % count, (i + 1) % count])
+            .collect();
+        (centres, neighbours)
+    }
+
+    fn continental_faces(crust: &[Crust]) -> usize {
+        crust.iter().filter(|crust| crust.continental()).count()
+    }
+
+    fn continental_thickness(crust: &[Crust]) -> f32 {
+        crust
+            .iter()
+            .filter(|crust| crust.continental())
+            .map(|crust| crust.thickness.0)
+            .sum()
+    }
+
+    #[test]
+    fn ocean_goes_under_continent_and_continents_both_survive() {
+        let (upper, stuck) = collide(ocean(0), continent(1));
+        assert_eq!(upper.plate, 1);
+        assert!(stuck.is_none());
+
+        let (upper, stuck) = collide(continent(0), continent(1));
+        assert_eq!(upper.plate, 0);
+        assert!(upper.elevation > 0.5);
+        assert_eq!(stuck.map(|crust| crust.plate), Some(1));
+    }
+
+    #[test]
+    fn continent_pushed_into_continent_is_kept() {
+        // plate 0 is all continent and turns one face along into plate 1, also all continent,
+        // which isn't moving
+        let (centres, neighbours) = ring(12);
+        let before: Vec<Crust> = (0..12).map(|i| continent(usize::from(i >= 6))).collect();
+        let step = Quat::from_rotation_z(std::f32::consts::TAU / 12.0);
+
+        let after = carry_crust(&before, &[(0, step)], &centres, &neighbours);
+        assert_eq!(continental_faces(&after), continental_faces(&before));
+    }
+
+    #[test]
+    fn continent_with_nowhere_to_go_piles_up() {
+        let neighbours = vec![vec![1], vec![0]];
+        let mut after = vec![Some(continent(0)), Some(continent(1))];
+        leave_behind(continent(1), 1, 0, &mut after, &neighbours);
+
+        let after: Vec<Crust> = after.into_iter().flatten().collect();
+        assert_eq!(continental_faces(&after), 2);
+        assert!((continental_thickness(&after) - 3.0 * CrustThickness::MARGIN.0).abs() < 1e-5);
+    }
+}
@@ -1,4 +1,5 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 #[derive(States, Clone, Eq, PartialEq, Hash, Debug)]
 pub enum GameState {
@@ -6,12 +7,13 @@ pub enum GameState {
     Simulation,
 }
 
-#[derive(SubStates, Clone, Eq, PartialEq, Hash, Debug, Default)]
+#[derive(SubStates, Clone, Eq, PartialEq, Hash, Debug, Default, Serialize, Deserialize)]
 #[source(GameState = GameState::WorldGen)]
 pub enum WorldGenState {
     #[default]
     SeedPlates,
     GenPlates,
+    MergePlates,
     FinishedPlates,
     AssignPlateBoundaries,
     FinishedPlateBoundaries,
@@ -19,15 +21,19 @@ pub enum WorldGenState {
     FinishedContinents,
     GenPlateVelocities,
     FinishedPlateVelocities,
+    ClassifyBoundaries,
+    FinishedBoundaryClassification,
+    GenElevation,
     JustChill,
     Finished,
 }
 
-#[derive(SubStates, Clone, Eq, PartialEq, Hash, Debug, Default)]
+#[derive(SubStates, Clone, Eq, PartialEq, Hash, Debug, Default, Serialize, Deserialize)]
 #[source(GameState = GameState::Simulation)]
 pub enum SimulationState {
     #[default]
     Running,
+    Paused,
 }
 
 pub struct StatePlugin;
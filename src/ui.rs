@@ -1,8 +1,16 @@
 // in each state draw some words to the screen
 
 use bevy::prelude::*;
+use bevy_panorbit_camera::PanOrbitCamera;
 
-use crate::states::{SimulationState, WorldGenState};
+use crate::seed::WorldSeed;
+use crate::states::{GameState, SimulationState, WorldGenState};
+
+#[derive(Component)]
+struct SeedUiText;
+
+#[derive(Component)]
+struct RewindUiText;
 
 #[derive(Component)]
 struct GenPlatesUiText;
@@ -19,12 +27,65 @@ struct GenContinentsUiText;
 #[derive(Component)]
 struct FinishedContinentsUiText;
 
+#[derive(Component)]
+struct FinishedVelocitiesUiText;
+
+#[derive(Component)]
+struct ClassifyBoundariesUiText;
+
+#[derive(Component)]
+struct FinishedClassificationUiText;
+
+#[derive(Component)]
+struct GenElevationUiText;
+
 #[derive(Component)]
 struct JustChillUiText;
 
 #[derive(Component)]
 struct SimulationRunningUiText;
 
+#[derive(Component)]
+struct SimulationPausedUiText;
+
+fn setup_seed_ui(mut commands: Commands, seed: Res<WorldSeed>) {
+    commands.spawn((
+        Text::new(format!("Seed: {}", seed.0)),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(5.0),
+            left: Val::Px(5.0),
+            ..default()
+        },
+        SeedUiText,
+    ));
+}
+
+/// Loading a save can swap the seed out from under us
+fn update_seed_ui(seed: Res<WorldSeed>, mut q_text: Query<&mut Text, With<SeedUiText>>) {
+    for mut text in &mut q_text {
+        text.0 = format!("Seed: {}", seed.0);
+    }
+}
+
+fn setup_rewind_ui(mut commands: Commands) {
+    commands.spawn((
+        Text::new(
+            "Backspace to step back a stage\n\
+             1-6 to regenerate from plates, boundaries, continents,\n\
+             velocities, boundary types or elevation",
+        ),
+        TextLayout::new_with_justify(Justify::Right),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(5.0),
+            right: Val::Px(5.0),
+            ..default()
+        },
+        RewindUiText,
+    ));
+}
+
 fn setup_gen_plates_ui(mut commands: Commands) {
     commands.spawn((
         // Accepts a `String` or any type that converts into a `String`, such as `&str`
@@ -57,6 +118,20 @@ fn setup_finished_plates_ui(mut commands: Commands) {
         },
         FinishedPlatesUiText,
     ));
+    commands.spawn((
+        // Accepts a `String` or any type that converts into a `String`, such as `&str`
+        Text::new("Press R to re-generate plates"),
+        // Set the justification of the Text
+        TextLayout::new_with_justify(Justify::Center),
+        // Set the style of the Node itself.
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(50.0),
+            right: Val::Px(5.0),
+            ..default()
+        },
+        FinishedPlatesUiText,
+    ));
 }
 
 fn setup_assigned_boundaries_ui(mut commands: Commands) {
@@ -141,6 +216,74 @@ fn setup_gen_velocities_ui(mut commands: Commands) {
     ));
 }
 
+fn setup_finished_velocities_ui(mut commands: Commands) {
+    commands.spawn((
+        // Accepts a `String` or any type that converts into a `String`, such as `&str`
+        Text::new("Press space to continue to classifying plate boundaries"),
+        // Set the justification of the Text
+        TextLayout::new_with_justify(Justify::Center),
+        // Set the style of the Node itself.
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(5.0),
+            right: Val::Px(5.0),
+            ..default()
+        },
+        FinishedVelocitiesUiText,
+    ));
+}
+
+fn setup_classify_boundaries_ui(mut commands: Commands) {
+    commands.spawn((
+        // Accepts a `String` or any type that converts into a `String`, such as `&str`
+        Text::new("For classify_boundaries"),
+        // Set the justification of the Text
+        TextLayout::new_with_justify(Justify::Center),
+        // Set the style of the Node itself.
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(5.0),
+            right: Val::Px(5.0),
+            ..default()
+        },
+        ClassifyBoundariesUiText,
+    ));
+}
+
+fn setup_finished_classification_ui(mut commands: Commands) {
+    commands.spawn((
+        // Accepts a `String` or any type that converts into a `String`, such as `&str`
+        Text::new("Press space to continue to generating elevation"),
+        // Set the justification of the Text
+        TextLayout::new_with_justify(Justify::Center),
+        // Set the style of the Node itself.
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(5.0),
+            right: Val::Px(5.0),
+            ..default()
+        },
+        FinishedClassificationUiText,
+    ));
+}
+
+fn setup_gen_elevation_ui(mut commands: Commands) {
+    commands.spawn((
+        // Accepts a `String` or any type that converts into a `String`, such as `&str`
+        Text::new("For gen_elevation"),
+        // Set the justification of the Text
+        TextLayout::new_with_justify(Justify::Center),
+        // Set the style of the Node itself.
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(5.0),
+            right: Val::Px(5.0),
+            ..default()
+        },
+        GenElevationUiText,
+    ));
+}
+
 fn setup_just_chill_ui(mut commands: Commands) {
     commands.spawn((
         // Accepts a `String` or any type that converts into a `String`, such as `&str`
@@ -185,11 +328,53 @@ fn setup_simulation_running_ui(mut commands: Commands) {
             right: Val::Px(5.0),
             ..default()
         },
-        JustChillUiText,
+        SimulationRunningUiText,
     ));
 }
 
-fn cleanup_ui<T: Component>(mut commands: Commands, q: Query<Entity, With<T>>) {
+fn setup_simulation_paused_ui(mut commands: Commands) {
+    commands.spawn((
+        // Accepts a `String` or any type that converts into a `String`, such as `&str`
+        Text::new("The simulation is paused"),
+        // Set the justification of the Text
+        TextLayout::new_with_justify(Justify::Center),
+        // Set the style of the Node itself.
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(5.0),
+            right: Val::Px(5.0),
+            ..default()
+        },
+        SimulationPausedUiText,
+    ));
+}
+
+/// Dragging a `T` slider shouldn't spin the globe round behind it. The camera is only given back
+/// if it was this that took it away
+pub(crate) fn hold_camera_while_pressed<T: Component>(
+    q_slider: Query<&Interaction, With<T>>,
+    mut q_camera: Query<&mut PanOrbitCamera>,
+    mut camera_disabled: Local<bool>,
+) {
+    let dragging = q_slider
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed);
+    if dragging && !*camera_disabled {
+        for mut camera in &mut q_camera {
+            if camera.enabled {
+                camera.enabled = false;
+                *camera_disabled = true;
+            }
+        }
+    } else if !dragging && *camera_disabled {
+        for mut camera in &mut q_camera {
+            camera.enabled = true;
+        }
+        *camera_disabled = false;
+    }
+}
+
+pub(crate) fn cleanup_ui<T: Component>(mut commands: Commands, q: Query<Entity, With<T>>) {
     for entity_id in q.iter() {
         commands.entity(entity_id).despawn();
     }
@@ -199,7 +384,11 @@ pub struct UiPlugin;
 
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(WorldGenState::GenPlates), setup_gen_plates_ui)
+        app.add_systems(Startup, setup_seed_ui)
+            .add_systems(Update, update_seed_ui.run_if(resource_changed::<WorldSeed>))
+            .add_systems(OnEnter(GameState::WorldGen), setup_rewind_ui)
+            .add_systems(OnExit(GameState::WorldGen), cleanup_ui::<RewindUiText>)
+            .add_systems(OnEnter(WorldGenState::GenPlates), setup_gen_plates_ui)
             .add_systems(
                 OnExit(WorldGenState::GenPlates),
                 cleanup_ui::<GenPlatesUiText>,
@@ -244,6 +433,35 @@ impl Plugin for UiPlugin {
                 OnExit(WorldGenState::GenPlateVelocities),
                 cleanup_ui::<JustChillUiText>,
             )
+            .add_systems(
+                OnEnter(WorldGenState::FinishedPlateVelocities),
+                setup_finished_velocities_ui,
+            )
+            .add_systems(
+                OnExit(WorldGenState::FinishedPlateVelocities),
+                cleanup_ui::<FinishedVelocitiesUiText>,
+            )
+            .add_systems(
+                OnEnter(WorldGenState::ClassifyBoundaries),
+                setup_classify_boundaries_ui,
+            )
+            .add_systems(
+                OnExit(WorldGenState::ClassifyBoundaries),
+                cleanup_ui::<ClassifyBoundariesUiText>,
+            )
+            .add_systems(
+                OnEnter(WorldGenState::FinishedBoundaryClassification),
+                setup_finished_classification_ui,
+            )
+            .add_systems(
+                OnExit(WorldGenState::FinishedBoundaryClassification),
+                cleanup_ui::<FinishedClassificationUiText>,
+            )
+            .add_systems(OnEnter(WorldGenState::GenElevation), setup_gen_elevation_ui)
+            .add_systems(
+                OnExit(WorldGenState::GenElevation),
+                cleanup_ui::<GenElevationUiText>,
+            )
             .add_systems(OnEnter(WorldGenState::JustChill), setup_just_chill_ui)
             .add_systems(
                 OnExit(WorldGenState::JustChill),
@@ -256,6 +474,11 @@ impl Plugin for UiPlugin {
             .add_systems(
                 OnExit(SimulationState::Running),
                 cleanup_ui::<SimulationRunningUiText>,
+            )
+            .add_systems(OnEnter(SimulationState::Paused), setup_simulation_paused_ui)
+            .add_systems(
+                OnExit(SimulationState::Paused),
+                cleanup_ui::<SimulationPausedUiText>,
             );
     }
 }
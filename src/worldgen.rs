@@ -1,14 +1,83 @@
+use bevy::platform::collections::HashMap;
 use bevy::prelude::*;
-use rand::{Rng, seq::IndexedRandom, seq::index::sample};
-
-use crate::setup::{ChangeColour, Face, FaceNeighbours, N_PLATES, PlatePalette};
+use rand::{Rng, seq::IndexedRandom};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{PlatePartition, WorldGenConfig};
+use crate::crust::{CrustThickness, CrustType, gen_crust};
+use crate::elevation::{Elevation, gen_elevation};
+use crate::growth::{GrowthBias, forget_grown_sizes, log_plate_sizes, record_grown_sizes};
+use crate::partition::{Warp, merge_small_plates, voronoi_plates};
+use crate::seed::{RngStream, WorldRng};
+use crate::setup::{Face, FaceNeighbours, PlatePalette};
 use crate::states::{GameState, WorldGenState};
 
 #[derive(Component, Clone, Copy, PartialEq)]
 pub struct Plate(pub usize);
 
-#[derive(Component)]
-pub struct PlateBoundary;
+/// A side a face shares with a face on another plate
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundarySide {
+    /// The face on the other side
+    pub across: Entity,
+    /// Ends of the side on the unit sphere
+    pub ends: (Vec3, Vec3),
+    /// How the plate across moves against this one, `None` until the boundaries are classified
+    pub class: Option<BoundaryClass>,
+}
+
+/// The sides a face shares with faces on other plates, so a boundary runs along the edges
+/// between plates rather than through the faces either side of it
+#[derive(Component, Clone, Debug, PartialEq)]
+pub struct PlateBoundary {
+    pub sides: Vec<BoundarySide>,
+}
+
+impl PlateBoundary {
+    /// The boundary of a face on `plate` from each of its neighbours and the plate that's on,
+    /// `None` if they're all on the same plate
+    pub(crate) fn between<'a>(
+        face: &Face,
+        plate: usize,
+        neighbours: impl IntoIterator<Item = (Entity, &'a Face, usize)>,
+    ) -> Option<Self> {
+        let sides: Vec<BoundarySide> = neighbours
+            .into_iter()
+            .filter(|(_, _, neighbour_plate)| *neighbour_plate != plate)
+            .filter_map(|(across, neighbour, _)| {
+                let Some(ends) = face.shared_side(neighbour) else {
+                    // neighbours always share a side, so the corners have drifted apart
+                    warn!(
+                        "Faces {} and {} are neighbours but share no side",
+                        face.index, neighbour.index
+                    );
+                    return None;
+                };
+                Some(BoundarySide {
+                    across,
+                    ends,
+                    class: None,
+                })
+            })
+            .collect();
+        (!sides.is_empty()).then_some(Self { sides })
+    }
+
+    /// The sides `face` draws for its boundary. Each side is on the boundaries of the faces
+    /// both sides of it and only the lower numbered entity yields it, so going over every face's
+    /// own sides meets each side once
+    pub fn own_sides(&self, face: Entity) -> impl Iterator<Item = &BoundarySide> {
+        self.sides.iter().filter(move |side| side.across > face)
+    }
+
+    /// What the boundary does at this face as a whole, the class of its fastest moving side
+    pub fn class(&self) -> Option<BoundaryClass> {
+        self.sides
+            .iter()
+            .filter_map(|side| side.class)
+            .max_by(|a, b| a.magnitude.total_cmp(&b.magnitude))
+    }
+}
 
 #[derive(Component)]
 pub struct PlateGenFrontier;
@@ -19,61 +88,259 @@ pub struct Land;
 #[derive(Component)]
 pub struct Sea;
 
-#[derive(Event)]
-struct ResetContinents;
-
 #[derive(Component)]
 pub struct FacePlateVelocity {
     pub velocity: Vec3,
 }
-/// Select starting faces for flood fill
-fn seed_flood_fill(
+
+/// How the two plates either side of a boundary move relative to each other
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum BoundaryKind {
+    /// The plates move towards each other
+    Convergent,
+    /// The plates move apart
+    Divergent,
+    /// The plates slide past each other
+    Transform,
+}
+
+impl BoundaryKind {
+    pub fn colour(self) -> Color {
+        match self {
+            BoundaryKind::Convergent => Color::srgb(0.85, 0.1, 0.1),
+            BoundaryKind::Divergent => Color::srgb(0.1, 0.8, 0.9),
+            BoundaryKind::Transform => Color::srgb(0.95, 0.85, 0.1),
+        }
+    }
+}
+
+/// What's happening along a boundary side, `magnitude` is the speed of the relative plate motion
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BoundaryClass {
+    pub kind: BoundaryKind,
+    pub magnitude: f32,
+}
+
+/// The stages of world generation in the order they run.
+/// Each stage owns the per-face components it produces, so rewinding to a stage
+/// can throw away exactly what it and every later stage made.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum GenStage {
+    Plates,
+    PlateBoundaries,
+    Continents,
+    PlateVelocities,
+    BoundaryKinds,
+    Elevation,
+}
+
+impl GenStage {
+    pub const ALL: [GenStage; 6] = [
+        GenStage::Plates,
+        GenStage::PlateBoundaries,
+        GenStage::Continents,
+        GenStage::PlateVelocities,
+        GenStage::BoundaryKinds,
+        GenStage::Elevation,
+    ];
+
+    /// The stage that ran before this one
+    pub fn previous(self) -> Option<GenStage> {
+        match self {
+            GenStage::Plates => None,
+            GenStage::PlateBoundaries => Some(GenStage::Plates),
+            GenStage::Continents => Some(GenStage::PlateBoundaries),
+            GenStage::PlateVelocities => Some(GenStage::Continents),
+            GenStage::BoundaryKinds => Some(GenStage::PlateVelocities),
+            GenStage::Elevation => Some(GenStage::BoundaryKinds),
+        }
+    }
+
+    /// The state that (re)runs this stage
+    pub fn start_state(self) -> WorldGenState {
+        match self {
+            GenStage::Plates => WorldGenState::SeedPlates,
+            GenStage::PlateBoundaries => WorldGenState::AssignPlateBoundaries,
+            GenStage::Continents => WorldGenState::GenContinents,
+            GenStage::PlateVelocities => WorldGenState::GenPlateVelocities,
+            GenStage::BoundaryKinds => WorldGenState::ClassifyBoundaries,
+            GenStage::Elevation => WorldGenState::GenElevation,
+        }
+    }
+
+    /// The state we wait in once this stage is done
+    pub fn finished_state(self) -> WorldGenState {
+        match self {
+            GenStage::Plates => WorldGenState::FinishedPlates,
+            GenStage::PlateBoundaries => WorldGenState::FinishedPlateBoundaries,
+            GenStage::Continents => WorldGenState::FinishedContinents,
+            GenStage::PlateVelocities => WorldGenState::FinishedPlateVelocities,
+            GenStage::BoundaryKinds => WorldGenState::FinishedBoundaryClassification,
+            GenStage::Elevation => WorldGenState::JustChill,
+        }
+    }
+
+    /// The stage that is running or has just finished in this state
+    pub fn active_in(state: &WorldGenState) -> Option<GenStage> {
+        match state {
+            WorldGenState::SeedPlates
+            | WorldGenState::GenPlates
+            | WorldGenState::MergePlates
+            | WorldGenState::FinishedPlates => Some(GenStage::Plates),
+            WorldGenState::AssignPlateBoundaries | WorldGenState::FinishedPlateBoundaries => {
+                Some(GenStage::PlateBoundaries)
+            }
+            WorldGenState::GenContinents | WorldGenState::FinishedContinents => {
+                Some(GenStage::Continents)
+            }
+            WorldGenState::GenPlateVelocities | WorldGenState::FinishedPlateVelocities => {
+                Some(GenStage::PlateVelocities)
+            }
+            WorldGenState::ClassifyBoundaries | WorldGenState::FinishedBoundaryClassification => {
+                Some(GenStage::BoundaryKinds)
+            }
+            WorldGenState::GenElevation | WorldGenState::JustChill => Some(GenStage::Elevation),
+            WorldGenState::Finished => None,
+        }
+    }
+
+    /// The stage that has just finished, if we're waiting between stages
+    pub fn finished_in(state: &WorldGenState) -> Option<GenStage> {
+        GenStage::ALL
+            .into_iter()
+            .find(|stage| stage.finished_state() == *state)
+    }
+
+    /// Remove the components this stage put on a face
+    fn clear(self, entity: &mut EntityCommands) {
+        match self {
+            GenStage::Plates => {
+                entity.remove::<(Plate, PlateGenFrontier)>();
+            }
+            GenStage::PlateBoundaries => {
+                entity.remove::<PlateBoundary>();
+            }
+            GenStage::Continents => {
+                entity.remove::<(CrustType, CrustThickness)>();
+            }
+            GenStage::PlateVelocities => {
+                entity.remove::<FacePlateVelocity>();
+            }
+            GenStage::BoundaryKinds => {
+                // the classes live on the boundary sides, which belong to the boundaries stage
+                entity.entry::<PlateBoundary>().and_modify(|mut boundary| {
+                    for side in &mut boundary.sides {
+                        side.class = None;
+                    }
+                });
+            }
+            GenStage::Elevation => {
+                entity.remove::<Elevation>();
+            }
+        }
+    }
+}
+
+/// Throw away the output of `stage` and every stage after it.
+/// With `rerun` the stage is generated again, otherwise we wait where the previous stage finished
+/// (the first stage has nothing before it, so it always reruns).
+#[derive(Event)]
+pub struct Rewind {
+    pub stage: GenStage,
+    pub rerun: bool,
+}
+/// Select starting faces for the plates, then either flood fill out from them
+/// or split the globe between them all at once
+fn seed_plates(
     mut commands: Commands,
-    face_query: Query<Entity, With<Face>>,
-    palette: Res<PlatePalette>,
+    face_query: Query<(Entity, &Face)>,
+    config: Res<WorldGenConfig>,
+    mut world_rng: ResMut<WorldRng>,
+    mut palette: ResMut<PlatePalette>,
     mut gen_state: ResMut<NextState<WorldGenState>>,
 ) {
-    let face_entities: Vec<Entity> = face_query.into_iter().collect();
+    let face_entities: Vec<Entity> = face_query.iter().map(|(entity, _)| entity).collect();
+
+    // the plates are numbered afresh, so they take their colours as dealt again
+    if !palette.order().is_empty() {
+        palette.reset_order();
+    }
 
-    let mut rng = rand::rng();
+    let rng = world_rng.stream(RngStream::PlateSeeds);
 
     let starting_faces = face_entities
-        .choose_multiple(&mut rng, N_PLATES)
+        .choose_multiple(rng, config.n_plates)
         .copied()
         .collect::<Vec<_>>();
-    for (i, entity) in starting_faces.iter().enumerate() {
-        commands.entity(*entity).insert((
-            Plate(i),
-            ChangeColour { colour: palette[i] },
-            PlateGenFrontier,
-        ));
-    }
 
-    gen_state.set(WorldGenState::GenPlates);
+    match config.plate_partition {
+        PlatePartition::FloodFill => {
+            for (i, entity) in starting_faces.iter().enumerate() {
+                commands
+                    .entity(*entity)
+                    .insert((Plate(i), PlateGenFrontier));
+            }
+
+            let bias_rng = world_rng.stream(RngStream::GrowthBias);
+            commands.insert_resource(GrowthBias::new(
+                &config.plate_growth,
+                config.n_plates,
+                bias_rng,
+            ));
+
+            gen_state.set(WorldGenState::GenPlates);
+        }
+        PlatePartition::Voronoi {
+            relaxation,
+            warp,
+            warp_scale,
+        } => {
+            let warp = (warp > 0.0).then(|| Warp {
+                seed: world_rng.stream(RngStream::PartitionWarp).random(),
+                strength: warp,
+                scale: warp_scale,
+            });
+            let seeds: Vec<Vec3> = face_query
+                .iter_many(&starting_faces)
+                .map(|(_, face)| face.centre_pos)
+                .collect();
+            let centres: Vec<Vec3> = face_query.iter().map(|(_, face)| face.centre_pos).collect();
+
+            let plates = voronoi_plates(&centres, &seeds, relaxation, warp);
+            for ((entity, _), plate) in face_query.iter().zip(plates) {
+                commands.entity(entity).insert(Plate(plate));
+            }
+
+            gen_state.set(WorldGenState::MergePlates);
+        }
+    }
 }
 
 fn flood_fill(
     mut commands: Commands,
-    palette: Res<PlatePalette>,
-    q_faces: Query<(Entity, &FaceNeighbours, &Plate), With<PlateGenFrontier>>,
+    mut world_rng: ResMut<WorldRng>,
+    bias: Res<GrowthBias>,
+    q_faces: Query<(Entity, &Face, &FaceNeighbours, &Plate), With<PlateGenFrontier>>,
     q_regions: Query<&Plate>,
+    q_centres: Query<&Face>,
 ) {
-    let mut rng = rand::rng();
+    let rng = world_rng.stream(RngStream::PlateGrowth);
     // iterate through the faces that are on the frontier
-    for (face_entity_id, face_neighbours, region) in q_faces.iter() {
+    for (face_entity_id, face, face_neighbours, region) in q_faces.iter() {
         // choose a random neighbour for that face
-        if let Some(neighbour_entity) = face_neighbours.choose(&mut rng) {
+        if let Some(neighbour_entity) = face_neighbours.choose(rng) {
+            // the growth biases may turn the pick down, which slows the plate down in that direction
+            let claimed = bias.is_uniform()
+                || q_centres.get(*neighbour_entity).is_ok_and(|neighbour| {
+                    let weight = bias.weight(region.0, face.centre_pos, neighbour.centre_pos);
+                    rng.random_bool(f64::from(weight.clamp(0.0, 1.0)))
+                });
             // if the chosen neighour has not yet been assigned a region
-            if q_regions.get(*neighbour_entity).is_err() {
+            if claimed && q_regions.get(*neighbour_entity).is_err() {
                 // assign it to the current face's region and mark it as on the frontier
-                // also mark it to change colour
-                commands.entity(*neighbour_entity).insert((
-                    *region,
-                    ChangeColour {
-                        colour: palette[region.0],
-                    },
-                    PlateGenFrontier,
-                ));
+                commands
+                    .entity(*neighbour_entity)
+                    .insert((*region, PlateGenFrontier));
             }
         }
 
@@ -93,68 +360,89 @@ fn check_if_finished_plates(
     query_unassigned_faces: Query<Entity, (With<Face>, Without<Plate>)>,
 ) {
     if query_unassigned_faces.iter().len() == 0 {
-        state.set(WorldGenState::FinishedPlates);
+        state.set(WorldGenState::MergePlates);
     }
 }
 
-fn assign_plate_boundaries(
-    q_faces: Query<(Entity, &FaceNeighbours, &Plate)>,
-    q_regions: Query<&Plate>,
+/// Fold microplates and stray pieces of plates into their neighbours, then number the plates
+/// that are left from 0 so later stages see a clean partition
+fn merge_plates(
     mut commands: Commands,
+    config: Res<WorldGenConfig>,
+    mut palette: ResMut<PlatePalette>,
+    q_faces: Query<(Entity, &FaceNeighbours, &Plate)>,
     mut state: ResMut<NextState<WorldGenState>>,
 ) {
-    for (face_entity_id, face_neighbours, plate) in q_faces.iter() {
-        // if any neighbours are from a different region
-        if face_neighbours.iter().any(|&neighbour_entity_id| {
-            match q_regions.get(neighbour_entity_id) {
-                Ok(neighbour_plate) => neighbour_plate != plate,
-                Err(_) => unreachable!(), // this should be impossible, at this point every face should be assigned a plate
-            }
-        }) {
-            // this face is on a plate boundary
-            commands.entity(face_entity_id).insert((
-                PlateBoundary,
-                ChangeColour {
-                    colour: Color::BLACK,
-                },
-            ));
+    let slots: HashMap<Entity, usize> = q_faces
+        .iter()
+        .enumerate()
+        .map(|(i, (entity_id, ..))| (entity_id, i))
+        .collect();
+    let neighbours: Vec<Vec<usize>> = q_faces
+        .iter()
+        .map(|(_, face_neighbours, _)| {
+            face_neighbours
+                .iter()
+                .filter_map(|entity_id| slots.get(entity_id).copied())
+                .collect()
+        })
+        .collect();
+    let mut plates: Vec<usize> = q_faces.iter().map(|(.., plate)| plate.0).collect();
+
+    let order = merge_small_plates(
+        &mut plates,
+        &neighbours,
+        config.min_plate_size,
+        config.n_plates,
+    );
+
+    let n_left = plates.iter().max().map_or(0, |plate| plate + 1);
+    for ((entity_id, _, old), new) in q_faces.iter().zip(plates) {
+        if old.0 != new {
+            commands.entity(entity_id).insert(Plate(new));
         }
     }
+    // only touch the palette when something moved, changing it recolours the whole globe
+    if order.iter().enumerate().any(|(new, old)| new != *old) {
+        palette.reorder(&order);
+    }
+    if n_left < config.n_plates {
+        info!(
+            "Merged {} plates into their neighbours",
+            config.n_plates - n_left
+        );
+    }
 
-    state.set(WorldGenState::FinishedPlateBoundaries)
+    state.set(WorldGenState::FinishedPlates);
 }
 
-fn assign_continental_plates(
+fn assign_plate_boundaries(
+    q_faces: Query<(Entity, &Face, &FaceNeighbours, &Plate)>,
+    q_regions: Query<(&Face, &Plate)>,
     mut commands: Commands,
     mut state: ResMut<NextState<WorldGenState>>,
-    query_faces: Query<(Entity, &Plate), With<Face>>,
 ) {
-    let mut rng = rand::rng();
-
-    let ocean_plates = sample(&mut rng, N_PLATES, N_PLATES / 3).into_vec();
-
-    for (entity_id, plate) in query_faces.iter() {
-        if ocean_plates.contains(&plate.0) {
-            commands.entity(entity_id).insert((
-                Land,
-                ChangeColour {
-                    colour: Color::srgb(0.565, 0.933, 0.565),
-                },
-            ));
-        } else {
-            commands.entity(entity_id).insert((
-                Sea,
-                ChangeColour {
-                    colour: Color::srgb(0.0, 0.412, 0.58),
-                },
-            ));
+    for (face_entity_id, face, face_neighbours, plate) in q_faces.iter() {
+        // every face has a plate by now, so every neighbour is found
+        let neighbours = face_neighbours.iter().filter_map(|&neighbour_entity_id| {
+            q_regions
+                .get(neighbour_entity_id)
+                .ok()
+                .map(|(neighbour, neighbour_plate)| {
+                    (neighbour_entity_id, neighbour, neighbour_plate.0)
+                })
+        });
+        // if any sides are shared with a different region this face is on a plate boundary
+        if let Some(boundary) = PlateBoundary::between(face, plate.0, neighbours) {
+            commands.entity(face_entity_id).insert(boundary);
         }
     }
 
-    state.set(WorldGenState::FinishedContinents);
+    state.set(WorldGenState::FinishedPlateBoundaries);
 }
 
 fn handle_finished_plates(
+    mut commands: Commands,
     keyboard_input: Res<ButtonInput<KeyCode>>,
     mut state: ResMut<NextState<WorldGenState>>,
 ) {
@@ -162,10 +450,10 @@ fn handle_finished_plates(
         state.set(WorldGenState::AssignPlateBoundaries);
     }
     if keyboard_input.just_pressed(KeyCode::KeyR) {
-        // remove the colour from every face
-        // remove the `Plate` component from every face
-        // seed the flood fill
-        // change state to GenPlates
+        commands.trigger(Rewind {
+            stage: GenStage::Plates,
+            rerun: true,
+        });
     }
 }
 
@@ -187,17 +475,24 @@ fn handle_finished_continents(
         state.set(WorldGenState::GenPlateVelocities);
     }
     if keyboard_input.just_pressed(KeyCode::KeyR) {
-        commands.trigger(ResetContinents);
+        commands.trigger(Rewind {
+            stage: GenStage::Continents,
+            rerun: true,
+        });
     }
 }
 
 fn do_plate_velocities(
     mut commands: Commands,
     query_faces: Query<(Entity, &Face, &Plate)>,
+    mut world_rng: ResMut<WorldRng>,
+    config: Res<WorldGenConfig>,
     mut state: ResMut<NextState<WorldGenState>>,
 ) {
-    let plate_rotation_vectors: Vec<Vec3> =
-        (0..N_PLATES).map(|_| random_rotation_vector()).collect();
+    let rng = world_rng.stream(RngStream::PlateVelocities);
+    let plate_rotation_vectors: Vec<Vec3> = (0..config.n_plates)
+        .map(|_| random_rotation_vector(rng))
+        .collect();
 
     for (entity_id, face, plate) in query_faces.iter() {
         let face_velocity = plate_rotation_vectors[plate.0].cross(face.centre_pos);
@@ -206,15 +501,13 @@ fn do_plate_velocities(
         });
     }
 
-    state.set(WorldGenState::JustChill);
+    state.set(WorldGenState::FinishedPlateVelocities);
 }
 
 /// Generates a random angular velocity vector with length <= 1
-fn random_rotation_vector() -> Vec3 {
-    let mut rng = rand::rng();
-
+fn random_rotation_vector(rng: &mut impl Rng) -> Vec3 {
     // Random unit direction
-    let dir = random_unit_vector(&mut rng);
+    let dir = random_unit_vector(rng);
 
     // Random speed in [0.0, 1.0]
     let speed = rng.random_range(0.0..=1.0);
@@ -222,8 +515,37 @@ fn random_rotation_vector() -> Vec3 {
     dir * speed
 }
 
+/// Every face on a plate moves as `rotation × position`, so each plate's rotation is the
+/// least squares solution of `Σ (I - r rᵀ) ω = Σ r × v` over its faces.
+/// Working it out from the velocities means it doesn't matter whether they were generated or loaded.
+pub fn fit_plate_rotations(
+    faces: impl IntoIterator<Item = (Vec3, usize, Vec3)>,
+) -> HashMap<usize, Vec3> {
+    let mut sums: HashMap<usize, (Mat3, Vec3, f32)> = HashMap::new();
+    for (position, plate, velocity) in faces {
+        let r = position.normalize();
+        let (a, b, n) = sums.entry(plate).or_insert((Mat3::ZERO, Vec3::ZERO, 0.0));
+        *a += Mat3::IDENTITY - Mat3::from_cols(r * r.x, r * r.y, r * r.z);
+        *b += r.cross(velocity);
+        *n += 1.0;
+    }
+
+    sums.into_iter()
+        .map(|(plate, (a, b, n))| {
+            // a plate of one face only pins the rotation down to a line,
+            // the part of it that isn't along the face is the best we can do
+            let rotation = if a.determinant().abs() > 1e-6 {
+                a.inverse() * b
+            } else {
+                b / n
+            };
+            (plate, rotation)
+        })
+        .collect()
+}
+
 /// Uniformly samples a random unit vector on the sphere
-fn random_unit_vector(rng: &mut impl Rng) -> Vec3 {
+pub(crate) fn random_unit_vector(rng: &mut impl Rng) -> Vec3 {
     let u: f32 = rng.random_range(-1.0..=1.0);
     let theta: f32 = rng.random_range(0.0..=std::f32::consts::TAU);
 
@@ -235,6 +557,81 @@ fn random_unit_vector(rng: &mut impl Rng) -> Vec3 {
     Vec3::new(x, y, z)
 }
 
+fn handle_finished_plate_velocities(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut state: ResMut<NextState<WorldGenState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Space) {
+        state.set(WorldGenState::ClassifyBoundaries);
+    }
+}
+
+/// Work out whether each boundary side is convergent, divergent or transform
+/// from the motion of the plate across it
+fn classify_plate_boundaries(
+    mut q_boundaries: Query<(&Face, &FacePlateVelocity, &mut PlateBoundary)>,
+    q_across: Query<(&Face, &FacePlateVelocity)>,
+    mut state: ResMut<NextState<WorldGenState>>,
+) {
+    for (face, velocity, mut boundary) in &mut q_boundaries {
+        for side in &mut boundary.sides {
+            side.class = q_across
+                .get(side.across)
+                .ok()
+                .map(|(across, across_velocity)| {
+                    classify_boundary(
+                        face.centre_pos,
+                        velocity.velocity,
+                        across.centre_pos,
+                        across_velocity.velocity,
+                    )
+                });
+        }
+    }
+
+    state.set(WorldGenState::FinishedBoundaryClassification);
+}
+
+/// Classify a boundary side from the position and velocity of the faces either side of it
+pub(crate) fn classify_boundary(
+    position: Vec3,
+    velocity: Vec3,
+    across_position: Vec3,
+    across_velocity: Vec3,
+) -> BoundaryClass {
+    // direction across the side, in the plane tangent to the sphere at this face
+    let across = across_position - position;
+    let boundary_normal = (across - across.dot(position) * position).normalize_or_zero();
+
+    // how the plate across moves as seen from this one
+    let relative_velocity = across_velocity - velocity;
+    let normal_speed = relative_velocity.dot(boundary_normal);
+    let tangent_speed = (relative_velocity - normal_speed * boundary_normal).length();
+
+    // a plate moving back towards us (against the boundary normal) is converging
+    let kind = if tangent_speed > normal_speed.abs() {
+        BoundaryKind::Transform
+    } else if normal_speed < 0.0 {
+        BoundaryKind::Convergent
+    } else {
+        BoundaryKind::Divergent
+    };
+
+    BoundaryClass {
+        kind,
+        magnitude: relative_velocity.length(),
+    }
+}
+
+fn handle_finished_boundary_classification(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut state: ResMut<NextState<WorldGenState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Space) {
+        state.set(WorldGenState::GenElevation);
+    }
+}
+
 fn handle_just_chill(
     keyboard_input: Res<ButtonInput<KeyCode>>,
     mut game_state: ResMut<NextState<GameState>>,
@@ -246,60 +643,144 @@ fn handle_just_chill(
     }
 }
 
-fn reset_continents(
-    _: On<ResetContinents>,
+/// Backspace steps back over the stage that just finished,
+/// the number keys regenerate from that stage onwards
+fn handle_rewind(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    state: Res<State<WorldGenState>>,
+) {
+    let Some(current) = GenStage::finished_in(state.get()) else {
+        return;
+    };
+
+    if keyboard_input.just_pressed(KeyCode::Backspace) {
+        commands.trigger(Rewind {
+            stage: current,
+            rerun: false,
+        });
+        return;
+    }
+
+    let stage_keys = [
+        KeyCode::Digit1,
+        KeyCode::Digit2,
+        KeyCode::Digit3,
+        KeyCode::Digit4,
+        KeyCode::Digit5,
+        KeyCode::Digit6,
+    ];
+    for (key, stage) in stage_keys.into_iter().zip(GenStage::ALL) {
+        if stage <= current && keyboard_input.just_pressed(key) {
+            commands.trigger(Rewind { stage, rerun: true });
+            return;
+        }
+    }
+}
+
+fn rewind(
+    rewind: On<Rewind>,
     mut commands: Commands,
     mut state: ResMut<NextState<WorldGenState>>,
-    query_faces: Query<Entity, Or<(With<Land>, With<Sea>)>>,
+    query_faces: Query<Entity, With<Face>>,
 ) {
-    for entity in query_faces.iter() {
-        commands.entity(entity).remove::<(Land, Sea)>();
+    let kept = rewind.stage.previous();
+
+    for entity_id in query_faces.iter() {
+        let mut entity = commands.entity(entity_id);
+        for stage in GenStage::ALL
+            .into_iter()
+            .filter(|stage| *stage >= rewind.stage)
+        {
+            stage.clear(&mut entity);
+        }
+    }
+
+    if rewind.rerun {
+        state.set(rewind.stage.start_state());
+    } else {
+        state.set(kept.map_or(rewind.stage.start_state(), GenStage::finished_state));
     }
-    state.set(WorldGenState::GenContinents);
 }
 
+/// The world generation stages themselves, these run the same with or without a window
 pub struct WorldGenPlugin;
 
 impl Plugin for WorldGenPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GrowthBias>()
+            .add_systems(
+                Update,
+                seed_plates.run_if(in_state(WorldGenState::SeedPlates)),
+            )
+            .add_systems(
+                FixedUpdate,
+                ((flood_fill, check_if_finished_plates).chain())
+                    .run_if(in_state(WorldGenState::GenPlates)),
+            )
+            .add_systems(
+                FixedUpdate,
+                (merge_plates).run_if(in_state(WorldGenState::MergePlates)),
+            )
+            .add_systems(OnEnter(WorldGenState::MergePlates), record_grown_sizes)
+            .add_systems(OnEnter(WorldGenState::FinishedPlates), log_plate_sizes)
+            .add_systems(OnExit(WorldGenState::FinishedPlates), forget_grown_sizes)
+            .add_systems(
+                FixedUpdate,
+                (assign_plate_boundaries).run_if(in_state(WorldGenState::AssignPlateBoundaries)),
+            )
+            .add_systems(
+                FixedUpdate,
+                gen_crust.run_if(in_state(WorldGenState::GenContinents)),
+            )
+            .add_systems(
+                FixedUpdate,
+                (do_plate_velocities).run_if(in_state(WorldGenState::GenPlateVelocities)),
+            )
+            .add_systems(
+                FixedUpdate,
+                (classify_plate_boundaries).run_if(in_state(WorldGenState::ClassifyBoundaries)),
+            )
+            .add_systems(
+                FixedUpdate,
+                (gen_elevation).run_if(in_state(WorldGenState::GenElevation)),
+            )
+            .add_observer(rewind);
+    }
+}
+
+/// Keyboard controls for stepping through the world generation stages
+pub struct WorldGenControlsPlugin;
+
+impl Plugin for WorldGenControlsPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(
-            Update,
-            seed_flood_fill.run_if(in_state(WorldGenState::SeedPlates)),
-        )
-        .add_systems(
-            FixedUpdate,
-            ((flood_fill, check_if_finished_plates).chain())
-                .run_if(in_state(WorldGenState::GenPlates)),
-        )
-        .add_systems(
             Update,
             (handle_finished_plates).run_if(in_state(WorldGenState::FinishedPlates)),
         )
-        .add_systems(
-            FixedUpdate,
-            (assign_plate_boundaries).run_if(in_state(WorldGenState::AssignPlateBoundaries)),
-        )
         .add_systems(
             Update,
             (handle_finished_plate_boundaries)
                 .run_if(in_state(WorldGenState::FinishedPlateBoundaries)),
         )
         .add_systems(
-            FixedUpdate,
-            (assign_continental_plates).run_if(in_state(WorldGenState::GenContinents)),
+            Update,
+            (handle_finished_continents).run_if(in_state(WorldGenState::FinishedContinents)),
         )
         .add_systems(
             Update,
-            (handle_finished_continents).run_if(in_state(WorldGenState::FinishedContinents)),
+            (handle_finished_plate_velocities)
+                .run_if(in_state(WorldGenState::FinishedPlateVelocities)),
         )
         .add_systems(
-            FixedUpdate,
-            (do_plate_velocities).run_if(in_state(WorldGenState::GenPlateVelocities)),
+            Update,
+            (handle_finished_boundary_classification)
+                .run_if(in_state(WorldGenState::FinishedBoundaryClassification)),
         )
         .add_systems(
             Update,
             (handle_just_chill).run_if(in_state(WorldGenState::JustChill)),
         )
-        .add_observer(reset_continents);
+        .add_systems(Update, handle_rewind.run_if(in_state(GameState::WorldGen)));
     }
 }